@@ -0,0 +1,412 @@
+//! Instructions, constructors and account state for the address lookup table program.
+//!
+//! Address lookup tables store up to [`LOOKUP_TABLE_MAX_ADDRESSES`] addresses on chain so that
+//! v0 transactions can reference them by a one byte index instead of the full key. See
+//! [`MessageV0::try_compile`] for how a table is used when compiling a message.
+//!
+//! A table is created at a program derived address of its authority and a recent slot,
+//! extended with new addresses, and can later be frozen (made immutable) or deactivated and
+//! closed to reclaim its rent.
+//!
+//! [`MessageV0::try_compile`]: crate::types::MessageV0::try_compile
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    system_instruction,
+    types::{AccountMeta, AddressLookupTableAccount, Instruction, Pubkey, Slot},
+};
+
+/// `AddressLookupTab1e1111111111111111111111111`
+pub const ID: Pubkey = Pubkey([
+    2, 119, 166, 175, 151, 51, 155, 122, 200, 141, 24, 146, 201, 4, 70, 245, 0, 2, 48, 146, 102,
+    246, 46, 83, 193, 24, 36, 73, 130, 0, 0, 0,
+]);
+
+/// The maximum number of addresses that a lookup table can hold.
+pub const LOOKUP_TABLE_MAX_ADDRESSES: usize = 256;
+
+/// The serialized size of lookup table metadata.
+pub const LOOKUP_TABLE_META_SIZE: usize = 56;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ProgramInstruction {
+    /// Create an address lookup table
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Uninitialized address lookup table account
+    ///   1. `[SIGNER]` Account used to derive and control the new address lookup table.
+    ///   2. `[SIGNER, WRITE]` Account that will fund the new address lookup table.
+    ///   3. `[]` System program for CPI.
+    CreateLookupTable {
+        /// A recent slot must be used in the derivation path
+        /// for each initialized table. When closing table accounts,
+        /// the initialization slot must no longer be "recent" to prevent
+        /// address tables from being recreated with reordered or
+        /// otherwise malicious addresses.
+        recent_slot: Slot,
+        /// Address tables are always initialized at program-derived
+        /// addresses using the funding address, recent blockhash, and
+        /// the user-passed `bump_seed`.
+        bump_seed: u8,
+    },
+
+    /// Permanently freeze an address lookup table, making it immutable.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Address lookup table account to freeze
+    ///   1. `[SIGNER]` Current authority
+    FreezeLookupTable,
+
+    /// Extend an address lookup table with new addresses. Funding account and
+    /// system program account references are only required if the lookup table
+    /// account requires additional lamports to cover the rent-exempt balance
+    /// after being extended.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Address lookup table account to extend
+    ///   1. `[SIGNER]` Current authority
+    ///   2. `[SIGNER, WRITE, OPTIONAL]` Account that will fund the table reallocation
+    ///   3. `[OPTIONAL]` System program for CPI.
+    ExtendLookupTable { new_addresses: Vec<Pubkey> },
+
+    /// Deactivate an address lookup table, making it unusable and
+    /// eligible for closure after a short period of time.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Address lookup table account to deactivate
+    ///   1. `[SIGNER]` Current authority
+    DeactivateLookupTable,
+
+    /// Close an address lookup table account
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Address lookup table account to close
+    ///   1. `[SIGNER]` Current authority
+    ///   2. `[WRITE]` Recipient of closed account lamports
+    CloseLookupTable,
+}
+
+/// Derives the address of a lookup table account from its authority and the slot it is
+/// created in.
+pub fn derive_lookup_table_address(authority_address: &Pubkey, recent_slot: Slot) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[authority_address.as_ref(), &recent_slot.to_le_bytes()],
+        &ID,
+    )
+}
+
+/// Constructs an instruction to create a table account and returns
+/// the instruction and the table account's derived address.
+pub fn create_lookup_table(
+    authority_address: &Pubkey,
+    payer_address: &Pubkey,
+    recent_slot: Slot,
+) -> (Instruction, Pubkey) {
+    let (lookup_table_address, bump_seed) =
+        derive_lookup_table_address(authority_address, recent_slot);
+    let instruction = Instruction::new_with_bincode(
+        ID,
+        &ProgramInstruction::CreateLookupTable {
+            recent_slot,
+            bump_seed,
+        },
+        vec![
+            AccountMeta::new(lookup_table_address, false),
+            AccountMeta::new_readonly(*authority_address, true),
+            AccountMeta::new(*payer_address, true),
+            AccountMeta::new_readonly(system_instruction::ID, false),
+        ],
+    );
+
+    (instruction, lookup_table_address)
+}
+
+/// Constructs an instruction that freezes an address lookup table
+/// so that it can never be closed or extended again. Empty lookup
+/// tables cannot be frozen.
+pub fn freeze_lookup_table(
+    lookup_table_address: &Pubkey,
+    authority_address: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bincode(
+        ID,
+        &ProgramInstruction::FreezeLookupTable,
+        vec![
+            AccountMeta::new(*lookup_table_address, false),
+            AccountMeta::new_readonly(*authority_address, true),
+        ],
+    )
+}
+
+/// Constructs an instruction which extends an address lookup
+/// table account with new addresses.
+///
+/// A `payer_address` is only needed if the table has to be topped up to stay rent exempt after
+/// growing.
+pub fn extend_lookup_table(
+    lookup_table_address: &Pubkey,
+    authority_address: &Pubkey,
+    payer_address: Option<&Pubkey>,
+    new_addresses: Vec<Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*lookup_table_address, false),
+        AccountMeta::new_readonly(*authority_address, true),
+    ];
+
+    if let Some(payer_address) = payer_address {
+        accounts.extend([
+            AccountMeta::new(*payer_address, true),
+            AccountMeta::new_readonly(system_instruction::ID, false),
+        ]);
+    }
+
+    Instruction::new_with_bincode(
+        ID,
+        &ProgramInstruction::ExtendLookupTable { new_addresses },
+        accounts,
+    )
+}
+
+/// Constructs an instruction that deactivates an address lookup
+/// table so that it cannot be extended again and will be unusable
+/// and eligible for closure after a short amount of time.
+pub fn deactivate_lookup_table(
+    lookup_table_address: &Pubkey,
+    authority_address: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bincode(
+        ID,
+        &ProgramInstruction::DeactivateLookupTable,
+        vec![
+            AccountMeta::new(*lookup_table_address, false),
+            AccountMeta::new_readonly(*authority_address, true),
+        ],
+    )
+}
+
+/// Returns an instruction that closes an address lookup table
+/// account. The account will be deallocated and the lamports
+/// will be drained to the recipient address.
+pub fn close_lookup_table(
+    lookup_table_address: &Pubkey,
+    authority_address: &Pubkey,
+    recipient_address: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bincode(
+        ID,
+        &ProgramInstruction::CloseLookupTable,
+        vec![
+            AccountMeta::new(*lookup_table_address, false),
+            AccountMeta::new_readonly(*authority_address, true),
+            AccountMeta::new(*recipient_address, false),
+        ],
+    )
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AddressLookupTableError {
+    #[error("lookup table account data is invalid")]
+    InvalidAccountData,
+    #[error("lookup table account is uninitialized")]
+    UninitializedAccount,
+}
+
+/// Program account states
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+enum ProgramState {
+    /// Account is not initialized.
+    Uninitialized,
+    /// Initialized `LookupTable` account.
+    LookupTable(LookupTableMeta),
+}
+
+/// Address lookup table metadata
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LookupTableMeta {
+    /// Lookup tables cannot be closed until the deactivation slot is
+    /// no longer "recent" (not accessible in the `SlotHashes` sysvar).
+    pub deactivation_slot: Slot,
+    /// The slot that the table was last extended. Address tables may
+    /// only be used to lookup addresses that were extended before
+    /// the current bank's slot.
+    pub last_extended_slot: Slot,
+    /// The start index where the table was last extended from during
+    /// the `last_extended_slot`.
+    pub last_extended_slot_start_index: u8,
+    /// Authority address which must sign for each modification.
+    pub authority: Option<Pubkey>,
+    // Padding to keep addresses 8-byte aligned
+    _padding: u16,
+}
+
+impl LookupTableMeta {
+    /// Whether the table has been deactivated. Deactivated tables can no longer be extended, and
+    /// are closed once the deactivation slot is no longer recent.
+    pub fn is_deactivated(&self) -> bool {
+        self.deactivation_slot != Slot::MAX
+    }
+
+    /// Frozen tables have no authority and can never be modified again.
+    pub fn is_frozen(&self) -> bool {
+        self.authority.is_none()
+    }
+}
+
+/// The decoded contents of an address lookup table account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressLookupTable {
+    pub meta: LookupTableMeta,
+    pub addresses: Vec<Pubkey>,
+}
+
+impl AddressLookupTable {
+    /// Decodes the raw data of a lookup table account: a [`LOOKUP_TABLE_META_SIZE`] byte
+    /// header followed by the packed 32 byte addresses.
+    pub fn deserialize(data: &[u8]) -> Result<Self, AddressLookupTableError> {
+        let meta_data = data
+            .get(..LOOKUP_TABLE_META_SIZE)
+            .ok_or(AddressLookupTableError::InvalidAccountData)?;
+        let meta = match bincode::deserialize(meta_data) {
+            Ok(ProgramState::LookupTable(meta)) => meta,
+            Ok(ProgramState::Uninitialized) => {
+                return Err(AddressLookupTableError::UninitializedAccount)
+            }
+            Err(_) => return Err(AddressLookupTableError::InvalidAccountData),
+        };
+
+        let addresses_data = &data[LOOKUP_TABLE_META_SIZE..];
+        if !addresses_data.len().is_multiple_of(32) {
+            return Err(AddressLookupTableError::InvalidAccountData);
+        }
+        let addresses = addresses_data
+            .chunks_exact(32)
+            .map(|chunk| Pubkey::try_from(chunk).expect("chunk is 32 bytes"))
+            .collect();
+
+        Ok(Self { meta, addresses })
+    }
+
+    /// Converts the table into the form used to compile v0 messages.
+    pub fn into_account(self, key: Pubkey) -> AddressLookupTableAccount {
+        AddressLookupTableAccount {
+            key,
+            addresses: self.addresses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialize_table(meta: &LookupTableMeta, addresses: &[Pubkey]) -> Vec<u8> {
+        let mut data = bincode::serialize(&ProgramState::LookupTable(meta.clone())).unwrap();
+        data.resize(LOOKUP_TABLE_META_SIZE, 0);
+        for address in addresses {
+            data.extend_from_slice(address.as_ref());
+        }
+        data
+    }
+
+    #[test]
+    fn test_meta_size() {
+        let meta = LookupTableMeta {
+            deactivation_slot: Slot::MAX,
+            last_extended_slot: 0,
+            last_extended_slot_start_index: 0,
+            authority: Some(Pubkey::default()),
+            _padding: 0,
+        };
+        let data = bincode::serialize(&ProgramState::LookupTable(meta)).unwrap();
+        assert_eq!(data.len(), LOOKUP_TABLE_META_SIZE);
+    }
+
+    #[test]
+    fn test_deserialize_lookup_table() {
+        let meta = LookupTableMeta {
+            deactivation_slot: Slot::MAX,
+            last_extended_slot: 10,
+            last_extended_slot_start_index: 1,
+            authority: Some(Pubkey::from([1u8; 32])),
+            _padding: 0,
+        };
+        let addresses = vec![Pubkey::from([2u8; 32]), Pubkey::from([3u8; 32])];
+
+        let table = AddressLookupTable::deserialize(&serialize_table(&meta, &addresses)).unwrap();
+        assert_eq!(table.meta, meta);
+        assert_eq!(table.addresses, addresses);
+        assert!(!table.meta.is_deactivated());
+        assert!(!table.meta.is_frozen());
+    }
+
+    #[test]
+    fn test_deserialize_invalid_data() {
+        assert_eq!(
+            AddressLookupTable::deserialize(&[0u8; LOOKUP_TABLE_META_SIZE]),
+            Err(AddressLookupTableError::UninitializedAccount)
+        );
+        assert_eq!(
+            AddressLookupTable::deserialize(&[1u8; 10]),
+            Err(AddressLookupTableError::InvalidAccountData)
+        );
+
+        let meta = LookupTableMeta {
+            deactivation_slot: Slot::MAX,
+            last_extended_slot: 0,
+            last_extended_slot_start_index: 0,
+            authority: None,
+            _padding: 0,
+        };
+        let mut data = serialize_table(&meta, &[Pubkey::from([2u8; 32])]);
+        data.pop();
+        assert_eq!(
+            AddressLookupTable::deserialize(&data),
+            Err(AddressLookupTableError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_create_lookup_table_instruction() {
+        let authority = Pubkey::from([1u8; 32]);
+        let payer = Pubkey::from([2u8; 32]);
+
+        let (instruction, address) = create_lookup_table(&authority, &payer, 42);
+        let (expected_address, bump_seed) = derive_lookup_table_address(&authority, 42);
+        assert_eq!(address, expected_address);
+        assert_eq!(instruction.accounts[0], AccountMeta::new(address, false));
+        assert_eq!(
+            bincode::deserialize::<ProgramInstruction>(&instruction.data).unwrap(),
+            ProgramInstruction::CreateLookupTable {
+                recent_slot: 42,
+                bump_seed,
+            }
+        );
+    }
+
+    #[test]
+    fn test_extend_lookup_table_instruction() {
+        let table = Pubkey::from([1u8; 32]);
+        let authority = Pubkey::from([2u8; 32]);
+        let new_addresses = vec![Pubkey::from([3u8; 32])];
+
+        let instruction = extend_lookup_table(&table, &authority, None, new_addresses.clone());
+        assert_eq!(instruction.program_id, ID);
+        assert_eq!(instruction.accounts.len(), 2);
+        // u32 variant index, u64 vector length, then the addresses.
+        assert_eq!(instruction.data[..4], 2u32.to_le_bytes());
+        assert_eq!(instruction.data[4..12], 1u64.to_le_bytes());
+        assert_eq!(instruction.data[12..], new_addresses[0].to_bytes());
+
+        let payer = Pubkey::from([4u8; 32]);
+        let instruction = extend_lookup_table(&table, &authority, Some(&payer), new_addresses);
+        assert_eq!(instruction.accounts[2], AccountMeta::new(payer, true));
+        assert_eq!(
+            instruction.accounts[3],
+            AccountMeta::new_readonly(system_instruction::ID, false)
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Address derivation and instructions for the Associated Token Account program.
+//!
+//! An associated token account is the canonical token account of a wallet for a given mint,
+//! located at a program derived address of `[wallet, token_program_id, mint]`. Payers can
+//! create it on behalf of the wallet owner, so payouts to a new wallet can include its creation
+//! in the same transaction.
+
+use crate::{
+    system_instruction,
+    types::{AccountMeta, Instruction, Pubkey},
+};
+
+/// `ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL`
+pub const ID: Pubkey = Pubkey([
+    140, 151, 37, 143, 78, 36, 137, 241, 187, 61, 16, 41, 20, 142, 13, 131, 11, 90, 19, 153, 218,
+    255, 16, 132, 4, 142, 123, 216, 219, 233, 248, 89,
+]);
+
+/// Instructions supported by the associated token account program, encoded as a single byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssociatedTokenAccountInstruction {
+    /// Creates an associated token account for the given wallet address and token mint.
+    /// Returns an error if the account exists.
+    ///
+    /// # Account references
+    ///   0. `[WRITE, SIGNER]` Funding account (must be a system account)
+    ///   1. `[WRITE]` Associated token account address to be created
+    ///   2. `[]` Wallet address for the new associated token account
+    ///   3. `[]` The token mint for the new associated token account
+    ///   4. `[]` System program
+    ///   5. `[]` SPL Token program
+    Create,
+    /// Creates an associated token account for the given wallet address and token mint,
+    /// if it doesn't already exist. Returns an error if the account exists, but with a
+    /// different owner.
+    ///
+    /// Takes the same accounts as [`AssociatedTokenAccountInstruction::Create`].
+    CreateIdempotent,
+}
+
+impl From<AssociatedTokenAccountInstruction> for u8 {
+    fn from(instruction: AssociatedTokenAccountInstruction) -> Self {
+        match instruction {
+            AssociatedTokenAccountInstruction::Create => 0,
+            AssociatedTokenAccountInstruction::CreateIdempotent => 1,
+        }
+    }
+}
+
+/// Derives the associated token account address for the given wallet address, token mint and
+/// token program id.
+pub fn get_associated_token_address(
+    wallet_address: &Pubkey,
+    token_mint_address: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            wallet_address.as_ref(),
+            token_program_id.as_ref(),
+            token_mint_address.as_ref(),
+        ],
+        &ID,
+    )
+    .0
+}
+
+/// Creates an instruction to create an associated token account.
+///
+/// The transaction fails if the account already exists, see
+/// [`create_associated_token_account_idempotent`].
+pub fn create_associated_token_account(
+    funding_address: &Pubkey,
+    wallet_address: &Pubkey,
+    token_mint_address: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    build_associated_token_account_instruction(
+        funding_address,
+        wallet_address,
+        token_mint_address,
+        token_program_id,
+        AssociatedTokenAccountInstruction::Create,
+    )
+}
+
+/// Creates an instruction to create an associated token account if it doesn't exist yet.
+pub fn create_associated_token_account_idempotent(
+    funding_address: &Pubkey,
+    wallet_address: &Pubkey,
+    token_mint_address: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    build_associated_token_account_instruction(
+        funding_address,
+        wallet_address,
+        token_mint_address,
+        token_program_id,
+        AssociatedTokenAccountInstruction::CreateIdempotent,
+    )
+}
+
+fn build_associated_token_account_instruction(
+    funding_address: &Pubkey,
+    wallet_address: &Pubkey,
+    token_mint_address: &Pubkey,
+    token_program_id: &Pubkey,
+    instruction: AssociatedTokenAccountInstruction,
+) -> Instruction {
+    let associated_account_address =
+        get_associated_token_address(wallet_address, token_mint_address, token_program_id);
+
+    let account_metas = vec![
+        AccountMeta::new(*funding_address, true),
+        AccountMeta::new(associated_account_address, false),
+        AccountMeta::new_readonly(*wallet_address, false),
+        AccountMeta::new_readonly(*token_mint_address, false),
+        AccountMeta::new_readonly(system_instruction::ID, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    Instruction::new_with_bytes(ID, &[instruction.into()], account_metas)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{spl_token, spl_token_2022};
+
+    #[test]
+    fn test_get_associated_token_address() {
+        let wallet = Pubkey::from_str("7qbRF6YsyGuLUVs6Y1q64bdVrfe4ZcUUz1JRdoVNUJnm").unwrap();
+        let usdc = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+
+        let address = get_associated_token_address(&wallet, &usdc, &spl_token::ID);
+        assert_eq!(
+            address,
+            Pubkey::from_str("J4CoMqG21q9iZzdZWWdbQohgDvesiLLbb2dpBhZr4ua6").unwrap()
+        );
+        assert!(!address.is_on_curve());
+        assert_ne!(
+            address,
+            get_associated_token_address(&wallet, &usdc, &spl_token_2022::ID)
+        );
+    }
+
+    #[test]
+    fn test_create_idempotent_instruction() {
+        let payer = Pubkey::from([1u8; 32]);
+        let wallet = Pubkey::from([2u8; 32]);
+        let mint = Pubkey::from([3u8; 32]);
+
+        let instruction =
+            create_associated_token_account_idempotent(&payer, &wallet, &mint, &spl_token::ID);
+        assert_eq!(instruction.program_id, ID);
+        assert_eq!(instruction.data, vec![1]);
+        assert_eq!(
+            instruction.accounts[1].pubkey,
+            get_associated_token_address(&wallet, &mint, &spl_token::ID)
+        );
+        assert_eq!(
+            instruction.accounts[5],
+            AccountMeta::new_readonly(spl_token::ID, false)
+        );
+    }
+}
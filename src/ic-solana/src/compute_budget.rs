@@ -0,0 +1,242 @@
+//! Instructions and constructors for the compute budget program.
+//!
+//! Transactions request a compute unit limit and pay a price per compute unit, in
+//! micro-lamports, on top of the base fee. Under congestion, leaders prioritize transactions
+//! with a higher price, so the price is usually derived from the fees recently paid to write
+//! the same accounts, see [`RpcClient::add_compute_budget`].
+//!
+//! [`RpcClient::add_compute_budget`]: crate::rpc_client::RpcClient::add_compute_budget
+
+use crate::{
+    system_instruction::{self, SystemInstruction},
+    types::{CompiledInstruction, Instruction, Message, Pubkey, RpcPrioritizationFee},
+};
+
+/// `ComputeBudget111111111111111111111111111111`
+pub const ID: Pubkey = Pubkey([
+    3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231, 188, 140, 229, 187,
+    197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0,
+]);
+
+/// The maximum number of compute units a transaction can request.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Compute units consumed by each compute budget instruction.
+pub const COMPUTE_BUDGET_INSTRUCTION_COMPUTE_UNITS: u32 = 150;
+
+/// Compute budget program instructions supported by this crate.
+///
+/// Encoded as a one byte tag followed by the little-endian value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputeBudgetInstruction {
+    /// Set a specific compute unit limit that the transaction is allowed to consume.
+    SetComputeUnitLimit(u32),
+    /// Set a compute unit price in "micro-lamports" to pay a higher transaction
+    /// fee for higher transaction prioritization.
+    SetComputeUnitPrice(u64),
+}
+
+impl ComputeBudgetInstruction {
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(9);
+        match self {
+            Self::SetComputeUnitLimit(units) => {
+                buf.push(2);
+                buf.extend_from_slice(&units.to_le_bytes());
+            }
+            Self::SetComputeUnitPrice(micro_lamports) => {
+                buf.push(3);
+                buf.extend_from_slice(&micro_lamports.to_le_bytes());
+            }
+        }
+        buf
+    }
+}
+
+/// Create a `SetComputeUnitLimit` instruction.
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &ComputeBudgetInstruction::SetComputeUnitLimit(units).pack(),
+        vec![],
+    )
+}
+
+/// Create a `SetComputeUnitPrice` instruction.
+pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports).pack(),
+        vec![],
+    )
+}
+
+/// Returns the fee at the given percentile (0 to 100) of the recent prioritization fees, using
+/// the nearest-rank method. Returns 0 when there are no fees.
+pub fn prioritization_fee_percentile(fees: &[RpcPrioritizationFee], percentile: u8) -> u64 {
+    let mut fees: Vec<u64> = fees.iter().map(|fee| fee.prioritization_fee).collect();
+    if fees.is_empty() {
+        return 0;
+    }
+    fees.sort_unstable();
+
+    let rank = (percentile.min(100) as usize * fees.len()).div_ceil(100);
+    fees[rank.saturating_sub(1)]
+}
+
+/// Replaces the compute budget instructions of a compiled message with the given limit and
+/// price, placed before every other instruction.
+///
+/// The compute budget program is a read-only, unsigned account, so it is appended at the end of
+/// the account keys if missing and the indexes of the existing instructions don't change. The
+/// message has to be signed again afterwards.
+///
+/// If the message relies on a durable nonce, the budget instructions are placed right after the
+/// `AdvanceNonceAccount` instruction, which must stay first.
+pub fn prepend_compute_budget_instructions(
+    message: &mut Message,
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: Option<u64>,
+) {
+    let program_id_index = match message.account_keys.iter().position(|key| *key == ID) {
+        Some(index) => index,
+        None => {
+            message.account_keys.push(ID);
+            message.header.num_readonly_unsigned_accounts += 1;
+            message.account_keys.len() - 1
+        }
+    };
+    let program_id_index =
+        u8::try_from(program_id_index).expect("too many account keys in message");
+
+    let budget_instructions = compute_unit_limit
+        .map(ComputeBudgetInstruction::SetComputeUnitLimit)
+        .into_iter()
+        .chain(compute_unit_price.map(ComputeBudgetInstruction::SetComputeUnitPrice))
+        .map(|instruction| CompiledInstruction {
+            program_id_index,
+            accounts: vec![],
+            data: instruction.pack(),
+        });
+
+    let mut instructions = std::mem::take(&mut message.instructions)
+        .into_iter()
+        .filter(|instruction| instruction.program_id_index != program_id_index)
+        .peekable();
+
+    // A durable nonce transaction must keep advancing its nonce as the first instruction.
+    let advance_nonce =
+        instructions.next_if(|instruction| is_advance_nonce_instruction(message, instruction));
+
+    message.instructions = advance_nonce
+        .into_iter()
+        .chain(budget_instructions)
+        .chain(instructions)
+        .collect();
+}
+
+fn is_advance_nonce_instruction(message: &Message, instruction: &CompiledInstruction) -> bool {
+    message
+        .account_keys
+        .get(instruction.program_id_index as usize)
+        == Some(&system_instruction::ID)
+        && bincode::deserialize(&instruction.data).ok()
+            == Some(SystemInstruction::AdvanceNonceAccount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{nonce, types::BlockHash};
+
+    fn fees(values: &[u64]) -> Vec<RpcPrioritizationFee> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(slot, fee)| RpcPrioritizationFee {
+                slot: slot as u64,
+                prioritization_fee: *fee,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pack() {
+        assert_eq!(
+            ComputeBudgetInstruction::SetComputeUnitLimit(200_000).pack(),
+            vec![2, 64, 13, 3, 0]
+        );
+        assert_eq!(
+            ComputeBudgetInstruction::SetComputeUnitPrice(1).pack(),
+            vec![3, 1, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_prioritization_fee_percentile() {
+        assert_eq!(prioritization_fee_percentile(&[], 50), 0);
+
+        let fees = fees(&[500, 0, 100, 300, 200]);
+        assert_eq!(prioritization_fee_percentile(&fees, 0), 0);
+        assert_eq!(prioritization_fee_percentile(&fees, 50), 200);
+        assert_eq!(prioritization_fee_percentile(&fees, 75), 300);
+        assert_eq!(prioritization_fee_percentile(&fees, 100), 500);
+    }
+
+    #[test]
+    fn test_prepend_compute_budget_instructions() {
+        let from = Pubkey::from([1u8; 32]);
+        let to = Pubkey::from([2u8; 32]);
+        let mut message = Message::new_with_blockhash(
+            &[system_instruction::transfer(&from, &to, 1)],
+            Some(&from),
+            &BlockHash::default(),
+        );
+        let transfer = message.instructions[0].clone();
+
+        prepend_compute_budget_instructions(&mut message, Some(450), Some(1_000));
+        assert_eq!(message.account_keys.last(), Some(&ID));
+        assert_eq!(message.header.num_readonly_unsigned_accounts, 2);
+        assert_eq!(message.instructions.len(), 3);
+        assert_eq!(message.instructions[2], transfer);
+        assert_eq!(message.program_id(0), Some(&ID));
+        assert!(message.is_writable(1));
+        assert!(!message.is_writable(3));
+
+        // Existing budget instructions are replaced rather than duplicated.
+        prepend_compute_budget_instructions(&mut message, None, Some(2_000));
+        assert_eq!(message.account_keys.len(), 4);
+        assert_eq!(message.instructions.len(), 2);
+        assert_eq!(
+            message.instructions[0].data,
+            ComputeBudgetInstruction::SetComputeUnitPrice(2_000).pack()
+        );
+        assert_eq!(message.instructions[1], transfer);
+    }
+
+    #[test]
+    fn test_prepend_compute_budget_instructions_with_nonce() {
+        let from = Pubkey::from([1u8; 32]);
+        let to = Pubkey::from([2u8; 32]);
+        let nonce_account = Pubkey::from([3u8; 32]);
+        let nonce_data = nonce::Data {
+            authority: from,
+            ..Default::default()
+        };
+        let mut message = Message::new_with_nonce(
+            vec![system_instruction::transfer(&from, &to, 1)],
+            Some(&from),
+            &nonce_account,
+            &nonce_data,
+        );
+        let advance_nonce = message.instructions[0].clone();
+        let transfer = message.instructions[1].clone();
+
+        prepend_compute_budget_instructions(&mut message, Some(450), Some(1_000));
+        assert_eq!(message.instructions.len(), 4);
+        assert_eq!(message.instructions[0], advance_nonce);
+        assert_eq!(message.program_id(1), Some(&ID));
+        assert_eq!(message.program_id(2), Some(&ID));
+        assert_eq!(message.instructions[3], transfer);
+    }
+}
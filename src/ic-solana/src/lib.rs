@@ -1,9 +1,18 @@
+pub mod address_lookup_table;
+pub mod associated_token_account;
+pub mod compute_budget;
 pub mod constants;
 pub mod logs;
 pub mod metrics;
+pub mod nonce;
+pub mod outcalls;
 pub mod request;
 pub mod rpc_client;
+pub mod signer;
+pub mod spl_token;
+pub mod spl_token_2022;
 pub mod system_instruction;
+pub mod sysvar;
 pub mod types;
 pub mod utils;
 
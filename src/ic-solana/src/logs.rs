@@ -127,11 +127,12 @@ impl Log {
     }
 
     pub fn sort_asc(&mut self) {
-        self.entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        self.entries.sort_by_key(|entry| entry.timestamp);
     }
 
     pub fn sort_desc(&mut self) {
-        self.entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        self.entries
+            .sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
     }
 }
 
@@ -0,0 +1,132 @@
+//! State of durable transaction nonce accounts.
+//!
+//! A nonce account stores a blockhash that doesn't expire until the account is advanced. A
+//! transaction using it as its recent blockhash, and whose first instruction is
+//! [`advance_nonce_account`], stays valid for as long as needed, e.g. while waiting for
+//! threshold signatures.
+//!
+//! [`advance_nonce_account`]: crate::system_instruction::advance_nonce_account
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::types::{BlockHash, FeeCalculator, Pubkey};
+
+/// Size of the data of a nonce account.
+pub const NONCE_ACCOUNT_LENGTH: usize = 80;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum NonceError {
+    #[error("nonce account data is invalid")]
+    InvalidAccountData,
+    #[error("nonce account is uninitialized")]
+    UninitializedAccount,
+}
+
+/// The versioned wrapper of the nonce state stored in the account.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Versions {
+    Legacy(Box<State>),
+    /// Current variants have durable nonce and blockhash domains separated.
+    Current(Box<State>),
+}
+
+impl Versions {
+    pub fn state(&self) -> &State {
+        match self {
+            Self::Legacy(state) | Self::Current(state) => state,
+        }
+    }
+}
+
+/// The state of a durable transaction nonce account.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub enum State {
+    #[default]
+    Uninitialized,
+    Initialized(Data),
+}
+
+impl State {
+    /// Size of the serialized state, see [`NONCE_ACCOUNT_LENGTH`].
+    pub const fn size() -> usize {
+        NONCE_ACCOUNT_LENGTH
+    }
+
+    /// Decodes the data of a nonce account, failing if it hasn't been initialized.
+    pub fn initialized_data(account_data: &[u8]) -> Result<Data, NonceError> {
+        let versions: Versions =
+            bincode::deserialize(account_data).map_err(|_| NonceError::InvalidAccountData)?;
+        match versions.state() {
+            State::Initialized(data) => Ok(data.clone()),
+            State::Uninitialized => Err(NonceError::UninitializedAccount),
+        }
+    }
+}
+
+/// Initialized data of a durable transaction nonce account.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Data {
+    /// Address of the account that signs transactions using the durable nonce.
+    pub authority: Pubkey,
+    /// Durable nonce value derived from a valid previous blockhash.
+    pub durable_nonce: BlockHash,
+    /// The fee calculator associated with the blockhash.
+    pub fee_calculator: FeeCalculator,
+}
+
+impl Data {
+    /// The value to use as the recent blockhash of transactions relying on this nonce.
+    pub fn blockhash(&self) -> BlockHash {
+        self.durable_nonce
+    }
+
+    /// Fee per signature of transactions relying on this nonce.
+    pub fn get_lamports_per_signature(&self) -> u64 {
+        self.fee_calculator.lamports_per_signature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_account_length() {
+        let versions = Versions::Current(Box::new(State::Initialized(Data::default())));
+        assert_eq!(
+            bincode::serialize(&versions).unwrap().len(),
+            NONCE_ACCOUNT_LENGTH
+        );
+    }
+
+    #[test]
+    fn test_initialized_data() {
+        let data = Data {
+            authority: Pubkey::from([1u8; 32]),
+            durable_nonce: BlockHash::new(&[2u8; 32]),
+            fee_calculator: FeeCalculator {
+                lamports_per_signature: 5_000,
+            },
+        };
+        let account_data = bincode::serialize(&Versions::Current(Box::new(State::Initialized(
+            data.clone(),
+        ))))
+        .unwrap();
+
+        assert_eq!(State::initialized_data(&account_data), Ok(data));
+    }
+
+    #[test]
+    fn test_uninitialized_or_invalid_data() {
+        let account_data = bincode::serialize(&Versions::Current(Box::default())).unwrap();
+        assert_eq!(
+            State::initialized_data(&account_data),
+            Err(NonceError::UninitializedAccount)
+        );
+        assert_eq!(
+            State::initialized_data(&[1, 2, 3]),
+            Err(NonceError::InvalidAccountData)
+        );
+    }
+}
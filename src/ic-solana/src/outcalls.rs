@@ -0,0 +1,26 @@
+//! Costs of HTTP outcalls made by canisters.
+
+/// Number of nodes of the subnet the canister runs on. HTTP outcall fees scale with it.
+pub const SUBNET_SIZE: u128 = 13;
+
+/// Returns the cycles an HTTP outcall costs, see
+/// <https://internetcomputer.org/docs/current/developer-docs/gas-cost#http-outcalls>.
+pub fn http_request_cycles(request_bytes: u64, max_response_bytes: u64) -> u128 {
+    (3_000_000 + 60_000 * SUBNET_SIZE) * SUBNET_SIZE
+        + 400 * SUBNET_SIZE * request_bytes as u128
+        + 800 * SUBNET_SIZE * max_response_bytes as u128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_request_cycles() {
+        assert_eq!(http_request_cycles(0, 0), 49_140_000);
+        assert_eq!(
+            http_request_cycles(1_000, 16 * 1024),
+            49_140_000 + 5_200_000 + 170_393_600
+        );
+    }
+}
@@ -3,9 +3,11 @@ use std::{
     collections::{BTreeSet, HashMap},
     fmt::Debug,
     str::FromStr,
+    time::Duration,
 };
 
 use base64::{prelude::BASE64_STANDARD, Engine};
+use futures::channel::oneshot;
 use ic_canister_log::log;
 use ic_cdk::api::management_canister::http_request::{
     http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, TransformContext,
@@ -15,18 +17,24 @@ use serde_json::{json, Value};
 
 use crate::{
     add_metric_entry,
+    address_lookup_table::{self, AddressLookupTable},
+    associated_token_account::get_associated_token_address,
+    compute_budget::{prepend_compute_budget_instructions, prioritization_fee_percentile},
     constants::*,
+    nonce,
     request::RpcRequest,
     rpc_client::multi_call::{MultiCallError, MultiCallResults},
+    system_instruction,
     types::{
-        CommitmentConfig, EncodedConfirmedTransactionWithStatusMeta, Epoch, EpochInfo,
-        EpochSchedule, Pubkey, RpcAccountInfoConfig, RpcBlockConfig, RpcBlockProductionConfig,
-        RpcContextConfig, RpcEpochConfig, RpcGetVoteAccountsConfig, RpcLargestAccountsConfig,
-        RpcLeaderScheduleConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
-        RpcSignatureStatusConfig, RpcSignaturesForAddressConfig, RpcSimulateTransactionConfig,
-        RpcSupplyConfig, RpcTokenAccountsFilter, RpcTransactionConfig, Signature, Slot,
-        Transaction, TransactionStatus, UiAccount, UiConfirmedBlock, UiTokenAmount,
-        UiTransactionEncoding, UnixTimestamp,
+        AddressLookupTableAccount, CommitmentConfig, EncodedConfirmedTransactionWithStatusMeta,
+        Epoch, EpochInfo, EpochSchedule, Message, Pubkey, RpcAccountInfoConfig, RpcBlockConfig,
+        RpcBlockProductionConfig, RpcContextConfig, RpcEpochConfig, RpcGetVoteAccountsConfig,
+        RpcLargestAccountsConfig, RpcLeaderScheduleConfig, RpcProgramAccountsConfig,
+        RpcSendTransactionConfig, RpcSignatureStatusConfig, RpcSignaturesForAddressConfig,
+        RpcSimulateTransactionConfig, RpcSupplyConfig, RpcTokenAccountsFilter,
+        RpcTransactionConfig, Signature, Slot, Transaction, TransactionStatus, UiAccount,
+        UiAccountEncoding, UiConfirmedBlock, UiTokenAmount, UiTransactionEncoding, UnixTimestamp,
+        VersionedTransaction,
     },
 };
 
@@ -57,6 +65,8 @@ thread_local! {
     static NEXT_ID: RefCell<u64> = RefCell::default();
 }
 
+// Hooks are compared by address, which is only meaningful for identical configs.
+#[allow(unpredictable_function_pointer_comparisons)]
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct RpcClientConfig {
     pub response_consensus: Option<ConsensusStrategy>,
@@ -289,7 +299,7 @@ impl RpcClient {
         let bytes = Self::process_result(
             method,
             MultiCallResults::from_non_empty_iter(
-                self.providers.iter().cloned().zip(results.into_iter()),
+                self.providers.iter().cloned().zip(results),
             )
             .reduce(self.consensus_strategy()),
         )?;
@@ -319,7 +329,7 @@ impl RpcClient {
         let bytes = Self::process_result(
             Self::find_rpc_method_name(&payload),
             MultiCallResults::from_non_empty_iter(
-                self.providers.iter().cloned().zip(results.into_iter()),
+                self.providers.iter().cloned().zip(results),
             )
             .reduce(self.consensus_strategy()),
         )?;
@@ -345,6 +355,40 @@ impl RpcClient {
         .into()
     }
 
+    /// Fetches and decodes the address lookup table stored at the provided Pubkey.
+    ///
+    /// Method relies on the `getAccountInfo` RPC call to get the table account:
+    ///   https://solana.com/docs/rpc/http/getAccountInfo
+    pub async fn get_address_lookup_table(
+        &self,
+        pubkey: &Pubkey,
+    ) -> RpcResult<AddressLookupTableAccount> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        };
+        let account = self
+            .get_account_info(pubkey, Some(config))
+            .await?
+            .value
+            .ok_or_else(|| RpcError::Text(format!("Lookup table {pubkey} not found")))?;
+
+        if account.owner != address_lookup_table::ID.to_string() {
+            return Err(RpcError::Text(format!(
+                "Account {pubkey} is not owned by the address lookup table program"
+            )));
+        }
+
+        let data = account
+            .data
+            .decode()
+            .ok_or_else(|| RpcError::ParseError("base64 account data".to_string()))?;
+        let table = AddressLookupTable::deserialize(&data)
+            .map_err(|e| RpcError::ParseError(format!("address lookup table: {e}")))?;
+
+        Ok(table.into_account(*pubkey))
+    }
+
     /// Returns the lamport balance of the account of provided Pubkey.
     ///
     /// Method relies on the `getBalance` RPC call to get the balance:
@@ -741,6 +785,28 @@ impl RpcClient {
         .into()
     }
 
+    /// Prices a message at the given percentile of the fees recently paid to write its accounts,
+    /// and prepends the matching compute budget instructions to it.
+    ///
+    /// Returns the chosen compute unit price, in micro-lamports. The message must be signed after
+    /// this call.
+    ///
+    /// Method relies on the `getRecentPrioritizationFees` RPC call to get the prioritization fees:
+    ///   https://solana.com/docs/rpc/http/getRecentPrioritizationFees
+    pub async fn add_compute_budget(
+        &self,
+        message: &mut Message,
+        compute_unit_limit: Option<u32>,
+        percentile: u8,
+    ) -> RpcResult<u64> {
+        let writable_keys: Vec<Pubkey> = message.writable_keys().into_iter().copied().collect();
+        let fees = self.get_recent_prioritization_fees(&writable_keys).await?;
+        let compute_unit_price = prioritization_fee_percentile(&fees, percentile);
+
+        prepend_compute_budget_instructions(message, compute_unit_limit, Some(compute_unit_price));
+        Ok(compute_unit_price)
+    }
+
     /// Returns the token balance of an SPL Token account.
     ///
     /// Method relies on the `getTokenAccountBalance` RPC call to get the token balance:
@@ -797,6 +863,32 @@ impl RpcClient {
         .into()
     }
 
+    /// Returns the associated token account of `wallet` for `mint`, or `None` if it hasn't been
+    /// created yet.
+    ///
+    /// Method relies on the `getTokenAccountsByOwner` RPC call to look up the account:
+    ///   https://solana.com/docs/rpc/http/getTokenAccountsByOwner
+    pub async fn get_associated_token_account(
+        &self,
+        wallet: &Pubkey,
+        mint: &Pubkey,
+        token_program_id: &Pubkey,
+    ) -> RpcResult<Option<RpcKeyedAccount>> {
+        let address = get_associated_token_address(wallet, mint, token_program_id).to_string();
+        let accounts = self
+            .get_token_accounts_by_owner(
+                wallet,
+                RpcTokenAccountsFilter::Mint(mint.to_string()),
+                None,
+            )
+            .await?
+            .parse_value();
+
+        Ok(accounts
+            .into_iter()
+            .find(|keyed_account| keyed_account.pubkey == address))
+    }
+
     /// Returns the 20 largest accounts of a particular SPL Token type.
     ///
     /// Method relies on the `getTokenLargestAccounts` RPC call to get the token balance:
@@ -991,6 +1083,35 @@ impl RpcClient {
         .into()
     }
 
+    /// Returns the state of an initialized durable nonce account.
+    ///
+    /// Method relies on the `getAccountInfo` RPC call to get the nonce account data:
+    ///   https://solana.com/docs/rpc/http/getAccountInfo
+    pub async fn get_nonce_account(&self, pubkey: &Pubkey) -> RpcResult<nonce::Data> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        };
+        let account = self
+            .get_account_info(pubkey, Some(config))
+            .await?
+            .value
+            .ok_or_else(|| RpcError::Text(format!("Nonce account {pubkey} not found")))?;
+
+        if account.owner != system_instruction::ID.to_string() {
+            return Err(RpcError::Text(format!(
+                "Account {pubkey} is not owned by the system program"
+            )));
+        }
+
+        let data = account
+            .data
+            .decode()
+            .ok_or_else(|| RpcError::ParseError("base64 account data".to_string()))?;
+        nonce::State::initialized_data(&data)
+            .map_err(|e| RpcError::ParseError(format!("nonce account: {e}")))
+    }
+
     /// Returns all accounts owned by the provided program Pubkey.
     ///
     /// Method relies on the `getProgramAccounts` RPC call to get the program accounts:
@@ -1177,17 +1298,30 @@ impl RpcClient {
         tx: Transaction,
         config: RpcSendTransactionConfig,
     ) -> RpcResult<Signature> {
-        let serialized = tx.serialize();
+        let raw_tx = encode_transaction(tx.serialize(), config.encoding)?;
 
-        let raw_tx = match config.encoding {
-            None | Some(UiTransactionEncoding::Base58) => bs58::encode(serialized).into_string(),
-            Some(UiTransactionEncoding::Base64) => BASE64_STANDARD.encode(serialized),
-            Some(e) => {
-                return Err(RpcError::Text(format!(
-                    "Unsupported encoding: {e}. Supported encodings: base58, base64"
-                )));
-            }
-        };
+        let response: RpcResult<String> = self
+            .call(RpcRequest::SendTransaction, (raw_tx, config), Some(156))
+            .await?
+            .into();
+
+        Signature::from_str(response?.as_str())
+            .map_err(|_| RpcError::ParseError("Failed to parse signature".to_string()))
+    }
+
+    /// Submits a signed versioned transaction to the cluster for processing.
+    ///
+    /// Unlike [RpcClient::send_transaction], this accepts both legacy and v0 messages, so
+    /// transactions can load accounts from address lookup tables.
+    ///
+    /// Method relies on the `sendTransaction` RPC call to send the transaction:
+    ///   https://solana.com/docs/rpc/http/sendTransaction
+    pub async fn send_versioned_transaction(
+        &self,
+        tx: VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> RpcResult<Signature> {
+        let raw_tx = encode_transaction(tx.serialize(), config.encoding)?;
 
         let response: RpcResult<String> = self
             .call(RpcRequest::SendTransaction, (raw_tx, config), Some(156))
@@ -1198,6 +1332,85 @@ impl RpcClient {
             .map_err(|_| RpcError::ParseError("Failed to parse signature".to_string()))
     }
 
+    /// Submits a signed transaction and waits until it reaches the configured commitment, fails,
+    /// or can no longer land because its blockhash expired.
+    ///
+    /// `last_valid_block_height` is the one returned by [RpcClient::get_latest_blockhash] along
+    /// with the blockhash the transaction was signed with. While the status is unknown and the
+    /// blockhash is still valid, the transaction is re-broadcast after every poll, since RPC
+    /// nodes may drop it under load.
+    ///
+    /// Polls are spaced with IC timers, so each status check runs in its own message execution
+    /// rather than spinning inside the calling one. The calling update call stays open until
+    /// the transaction is confirmed or expires though, which blocks stopping the canister for
+    /// upgrades meanwhile, and its result is lost if the call fails. Callers who can't afford
+    /// that should send the transaction and check its status from a timer instead.
+    ///
+    /// Statuses are looked up in the whole transaction history, so a transaction that landed
+    /// before a slow poll isn't reported as expired.
+    pub async fn send_and_confirm_transaction(
+        &self,
+        tx: Transaction,
+        last_valid_block_height: u64,
+        config: RpcSendAndConfirmConfig,
+    ) -> RpcResult<TransactionConfirmation> {
+        let signature = self
+            .send_transaction(tx.clone(), config.send_config)
+            .await?;
+        let rebroadcast_config = RpcSendTransactionConfig {
+            skip_preflight: true,
+            ..config.send_config
+        };
+        let block_height_config = RpcContextConfig {
+            commitment: Some(config.commitment.commitment),
+            min_context_slot: None,
+        };
+
+        let status_config = RpcSignatureStatusConfig {
+            search_transaction_history: true,
+        };
+
+        loop {
+            sleep(config.poll_interval).await;
+
+            // Read before the status: a transaction landing in between would otherwise be
+            // reported as expired.
+            let block_height = self.get_block_height(Some(block_height_config)).await?;
+            let status = self
+                .get_signature_statuses(&[signature], Some(status_config.clone()))
+                .await?
+                .parse_value()
+                .pop()
+                .flatten();
+
+            if let Some(status) = status {
+                if let Some(err) = status.err {
+                    return Ok(TransactionConfirmation::Failed { signature, err });
+                }
+                if status.satisfies_commitment(config.commitment) {
+                    return Ok(TransactionConfirmation::Confirmed {
+                        signature,
+                        slot: status.slot,
+                    });
+                }
+                // Processed, but not at the requested commitment yet: it can't expire anymore.
+                continue;
+            }
+
+            if block_height > last_valid_block_height {
+                return Ok(TransactionConfirmation::Expired { signature });
+            }
+
+            // The node may already have the transaction, in which case it rejects the duplicate.
+            if let Err(err) = self.send_transaction(tx.clone(), rebroadcast_config).await {
+                log!(
+                    DEBUG,
+                    "Failed to re-broadcast transaction {signature}: {err}"
+                );
+            }
+        }
+    }
+
     /// Simulates sending a transaction.
     ///
     /// Method relies on the `simulateTransaction` RPC call to simulate the transaction:
@@ -1207,17 +1420,7 @@ impl RpcClient {
         tx: Transaction,
         config: RpcSimulateTransactionConfig,
     ) -> RpcResult<OptionalContext<RpcSimulateTransactionResult>> {
-        let serialized = tx.serialize();
-
-        let raw_tx = match config.encoding {
-            None | Some(UiTransactionEncoding::Base58) => bs58::encode(serialized).into_string(),
-            Some(UiTransactionEncoding::Base64) => BASE64_STANDARD.encode(serialized),
-            Some(e) => {
-                return Err(RpcError::Text(format!(
-                    "Unsupported encoding: {e}. Supported encodings: base58, base64"
-                )));
-            }
-        };
+        let raw_tx = encode_transaction(tx.serialize(), config.encoding)?;
 
         self.call(RpcRequest::SimulateTransaction, (raw_tx, config), None)
             .await?
@@ -1318,3 +1521,28 @@ impl RpcClient {
 //         write!(f, "{}", self.0)
 //     }
 // }
+
+/// Encodes a serialized transaction for the `sendTransaction` and `simulateTransaction` calls.
+fn encode_transaction(
+    serialized: Vec<u8>,
+    encoding: Option<UiTransactionEncoding>,
+) -> RpcResult<String> {
+    match encoding {
+        None | Some(UiTransactionEncoding::Base58) => Ok(bs58::encode(serialized).into_string()),
+        Some(UiTransactionEncoding::Base64) => Ok(BASE64_STANDARD.encode(serialized)),
+        Some(e) => Err(RpcError::Text(format!(
+            "Unsupported encoding: {e}. Supported encodings: base58, base64"
+        ))),
+    }
+}
+
+/// Resolves once `duration` has elapsed, using a one-off timer.
+async fn sleep(duration: Duration) {
+    let (sender, receiver) = oneshot::channel();
+    ic_cdk_timers::set_timer(duration, move || {
+        let _ = sender.send(());
+    });
+    // The sender can only be dropped without sending if the timer is cancelled, which never
+    // happens here.
+    let _ = receiver.await;
+}
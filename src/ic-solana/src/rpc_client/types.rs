@@ -1,4 +1,4 @@
-use std::{fmt::Debug, str::FromStr};
+use std::{fmt::Debug, str::FromStr, time::Duration};
 
 use candid::{CandidType, Deserialize};
 use ic_cdk::api::{
@@ -8,7 +8,9 @@ use ic_cdk::api::{
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::types::Cluster;
+use crate::types::{
+    Cluster, CommitmentConfig, RpcSendTransactionConfig, Signature, Slot, TransactionError,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize, CandidType)]
 pub enum ConsensusStrategy {
@@ -154,3 +156,57 @@ pub struct RpcConfig {
     #[serde(rename = "responseConsensus")]
     pub response_consensus: Option<ConsensusStrategy>,
 }
+
+/// Configuration of [`RpcClient::send_and_confirm_transaction`].
+///
+/// [`RpcClient::send_and_confirm_transaction`]: crate::rpc_client::RpcClient::send_and_confirm_transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RpcSendAndConfirmConfig {
+    /// Used for the initial submission. Re-broadcasts skip preflight checks.
+    pub send_config: RpcSendTransactionConfig,
+    /// The commitment the transaction must reach to be considered confirmed.
+    pub commitment: CommitmentConfig,
+    /// Delay between two status checks.
+    pub poll_interval: Duration,
+}
+
+impl Default for RpcSendAndConfirmConfig {
+    fn default() -> Self {
+        Self {
+            send_config: RpcSendTransactionConfig::default(),
+            commitment: CommitmentConfig::confirmed(),
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// The outcome of a transaction submitted with [`RpcClient::send_and_confirm_transaction`].
+///
+/// [`RpcClient::send_and_confirm_transaction`]: crate::rpc_client::RpcClient::send_and_confirm_transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionConfirmation {
+    /// The transaction reached the requested commitment.
+    Confirmed { signature: Signature, slot: Slot },
+    /// The transaction was executed, but failed.
+    Failed {
+        signature: Signature,
+        err: TransactionError,
+    },
+    /// The block height passed the last one valid for the transaction before it was processed.
+    ///
+    /// This only means it can no longer land if it was signed with a recent blockhash, in which
+    /// case it is safe to re-sign with a new one. A transaction signed with a durable nonce
+    /// doesn't expire: it can land until the nonce advances, which must be checked against the
+    /// nonce account before re-signing it.
+    Expired { signature: Signature },
+}
+
+impl TransactionConfirmation {
+    pub fn signature(&self) -> &Signature {
+        match self {
+            Self::Confirmed { signature, .. }
+            | Self::Failed { signature, .. }
+            | Self::Expired { signature } => signature,
+        }
+    }
+}
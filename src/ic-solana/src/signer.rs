@@ -0,0 +1,56 @@
+//! Signers for Solana transactions.
+//!
+//! Canisters cannot hold secret keys safely, so production signing is delegated to the threshold
+//! Schnorr (Ed25519) API of the IC management canister through [`ThresholdSigner`]. [`Keypair`]
+//! and [`TestSigner`] cover ephemeral keys and tests.
+
+use futures::future::LocalBoxFuture;
+use ic_cdk::api::call::RejectionCode;
+use thiserror::Error;
+
+use crate::types::{Pubkey, Signature};
+
+mod keypair;
+mod test_signer;
+mod threshold;
+
+pub use keypair::*;
+pub use test_signer::*;
+pub use threshold::*;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SignerError {
+    #[error("management canister call failed (code: {code:?}): {message}")]
+    ManagementCanisterError { code: RejectionCode, message: String },
+    #[error("invalid keypair: {0}")]
+    InvalidKeypair(String),
+    #[error("invalid public key: {0}")]
+    InvalidPublicKey(String),
+    #[error("invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("{0} is not a required signer of the message")]
+    NotASigner(Pubkey),
+    #[error("not enough signers")]
+    NotEnoughSigners,
+}
+
+impl From<(RejectionCode, String)> for SignerError {
+    fn from((code, message): (RejectionCode, String)) -> Self {
+        SignerError::ManagementCanisterError { code, message }
+    }
+}
+
+/// Produces Ed25519 signatures on behalf of a Solana address.
+///
+/// Signing is asynchronous because threshold signatures require a call to the management
+/// canister.
+pub trait Signer {
+    /// The address whose signature this signer produces.
+    fn pubkey(&self) -> Pubkey;
+
+    /// Signs the given message bytes.
+    fn sign_message<'a>(
+        &'a self,
+        message: &'a [u8],
+    ) -> LocalBoxFuture<'a, Result<Signature, SignerError>>;
+}
@@ -0,0 +1,66 @@
+use futures::future::LocalBoxFuture;
+use ic_crypto_ed25519::PrivateKey;
+
+use crate::{
+    signer::{Signer, SignerError},
+    types::{Pubkey, Signature, PUBKEY_BYTES},
+};
+
+/// Number of bytes in a keypair: the 32-byte secret key followed by the 32-byte public key.
+pub const KEYPAIR_LENGTH: usize = 64;
+
+/// An Ed25519 keypair held in canister memory.
+///
+/// Only use this for keys that are not worth protecting (e.g. ephemeral accounts); anything
+/// held in canister memory is visible to the node providers of the subnet. Prefer
+/// [`ThresholdSigner`](crate::signer::ThresholdSigner) for long-lived keys.
+pub struct Keypair {
+    secret: PrivateKey,
+    pubkey: Pubkey,
+}
+
+impl Keypair {
+    /// Creates a keypair from a 32-byte secret key.
+    pub fn from_secret_key(secret_key: &[u8]) -> Result<Self, SignerError> {
+        let secret = PrivateKey::deserialize_raw(secret_key)
+            .map_err(|e| SignerError::InvalidKeypair(format!("{e:?}")))?;
+        let pubkey = Pubkey::from(secret.public_key().serialize_raw());
+        Ok(Self { secret, pubkey })
+    }
+
+    /// Creates a keypair from the 64-byte format used by the Solana CLI, checking that the
+    /// public key half matches the secret key half.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignerError> {
+        if bytes.len() != KEYPAIR_LENGTH {
+            return Err(SignerError::InvalidKeypair(format!(
+                "expected {KEYPAIR_LENGTH} bytes, got {}",
+                bytes.len()
+            )));
+        }
+        let keypair = Self::from_secret_key(&bytes[..PUBKEY_BYTES])?;
+        if keypair.pubkey.as_ref() != &bytes[PUBKEY_BYTES..] {
+            return Err(SignerError::InvalidKeypair(
+                "public key does not match secret key".to_string(),
+            ));
+        }
+        Ok(keypair)
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature(self.secret.sign_message(message))
+    }
+}
+
+impl Signer for Keypair {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    fn sign_message<'a>(
+        &'a self,
+        message: &'a [u8],
+    ) -> LocalBoxFuture<'a, Result<Signature, SignerError>> {
+        let signature = self.sign(message);
+        Box::pin(async move { Ok(signature) })
+    }
+}
@@ -0,0 +1,33 @@
+use futures::future::LocalBoxFuture;
+use ic_sha3::Keccak256;
+
+use crate::{
+    signer::{Keypair, Signer, SignerError},
+    types::{Pubkey, Signature},
+};
+
+/// A deterministic signer for tests.
+///
+/// The key is derived from a label, so `TestSigner::new("payer")` always yields the same
+/// address, which keeps expected values in tests stable.
+pub struct TestSigner(Keypair);
+
+impl TestSigner {
+    pub fn new(label: &str) -> Self {
+        let seed = Keccak256::hash(label.as_bytes());
+        Self(Keypair::from_secret_key(&seed).expect("a 32-byte seed is always a valid secret key"))
+    }
+}
+
+impl Signer for TestSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.0.pubkey()
+    }
+
+    fn sign_message<'a>(
+        &'a self,
+        message: &'a [u8],
+    ) -> LocalBoxFuture<'a, Result<Signature, SignerError>> {
+        self.0.sign_message(message)
+    }
+}
@@ -0,0 +1,136 @@
+use std::{fmt, str::FromStr};
+
+use candid::CandidType;
+use futures::future::LocalBoxFuture;
+use ic_cdk::api::management_canister::schnorr::{
+    schnorr_public_key, sign_with_schnorr, SchnorrAlgorithm, SchnorrKeyId,
+    SchnorrPublicKeyArgument, SignWithSchnorrArgument,
+};
+use serde::Deserialize;
+
+use crate::{
+    signer::{Signer, SignerError},
+    types::{Pubkey, Signature},
+};
+
+/// Threshold Ed25519 keys made available by the management canister.
+///
+/// See https://internetcomputer.org/docs/current/developer-docs/smart-contracts/signatures/signing-messages-t-schnorr
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, CandidType, Deserialize)]
+pub enum SchnorrKey {
+    /// Only available on a local replica started by dfx.
+    #[default]
+    Dfx,
+    /// Test key available on the IC mainnet, backed by a subnet with fewer nodes.
+    Test,
+    /// Production key available on the IC mainnet.
+    Production,
+}
+
+impl SchnorrKey {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SchnorrKey::Dfx => "dfx_test_key",
+            SchnorrKey::Test => "test_key_1",
+            SchnorrKey::Production => "key_1",
+        }
+    }
+
+    pub fn key_id(&self) -> SchnorrKeyId {
+        SchnorrKeyId {
+            algorithm: SchnorrAlgorithm::Ed25519,
+            name: self.name().to_string(),
+        }
+    }
+}
+
+impl FromStr for SchnorrKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dfx_test_key" => Ok(SchnorrKey::Dfx),
+            "test_key_1" => Ok(SchnorrKey::Test),
+            "key_1" => Ok(SchnorrKey::Production),
+            _ => Err(format!("unknown schnorr key name: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for SchnorrKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Returns the Solana address controlled by the canister for the given derivation path.
+pub async fn threshold_public_key(
+    key: SchnorrKey,
+    derivation_path: Vec<Vec<u8>>,
+) -> Result<Pubkey, SignerError> {
+    let (response,) = schnorr_public_key(SchnorrPublicKeyArgument {
+        canister_id: None,
+        derivation_path,
+        key_id: key.key_id(),
+    })
+    .await?;
+
+    Pubkey::try_from(response.public_key.as_slice())
+        .map_err(|_| SignerError::InvalidPublicKey(bs58::encode(response.public_key).into_string()))
+}
+
+/// Signs messages with a threshold Ed25519 key derived for this canister.
+///
+/// Every derivation path yields a distinct key pair, so a canister can control several Solana
+/// accounts (e.g. one per purpose) from a single master key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThresholdSigner {
+    key: SchnorrKey,
+    derivation_path: Vec<Vec<u8>>,
+    pubkey: Pubkey,
+}
+
+impl ThresholdSigner {
+    /// Creates a signer, fetching its public key from the management canister.
+    pub async fn new(key: SchnorrKey, derivation_path: Vec<Vec<u8>>) -> Result<Self, SignerError> {
+        let pubkey = threshold_public_key(key, derivation_path.clone()).await?;
+        Ok(Self::with_pubkey(key, derivation_path, pubkey))
+    }
+
+    /// Creates a signer from a previously fetched public key.
+    pub fn with_pubkey(key: SchnorrKey, derivation_path: Vec<Vec<u8>>, pubkey: Pubkey) -> Self {
+        Self {
+            key,
+            derivation_path,
+            pubkey,
+        }
+    }
+
+    pub fn derivation_path(&self) -> &[Vec<u8>] {
+        &self.derivation_path
+    }
+}
+
+impl Signer for ThresholdSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    fn sign_message<'a>(
+        &'a self,
+        message: &'a [u8],
+    ) -> LocalBoxFuture<'a, Result<Signature, SignerError>> {
+        Box::pin(async move {
+            let (response,) = sign_with_schnorr(SignWithSchnorrArgument {
+                message: message.to_vec(),
+                derivation_path: self.derivation_path.clone(),
+                key_id: self.key.key_id(),
+            })
+            .await?;
+
+            Signature::try_from(response.signature.as_slice()).map_err(|_| {
+                SignerError::InvalidSignature(bs58::encode(response.signature).into_string())
+            })
+        })
+    }
+}
@@ -0,0 +1,407 @@
+//! Instructions and constructors for the SPL Token program.
+//!
+//! The builders take the token program ID as their first argument, like the upstream
+//! `spl-token` crate, because Token-2022 accepts the same instruction layout for every
+//! instruction defined here. Use [`ID`] for classic mints and [`spl_token_2022::ID`] for
+//! Token-2022 mints.
+//!
+//! Only single-signer authorities are supported: the authority of every instruction is always
+//! marked as a signer.
+//!
+//! [`spl_token_2022::ID`]: crate::spl_token_2022::ID
+
+use crate::types::{AccountMeta, Instruction, Pubkey};
+
+/// `TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA`
+pub const ID: Pubkey = Pubkey([
+    6, 221, 246, 225, 215, 101, 161, 147, 217, 203, 225, 70, 206, 235, 121, 172, 28, 180, 133, 237,
+    95, 91, 55, 145, 58, 140, 245, 133, 126, 255, 0, 169,
+]);
+
+/// The mint of wrapped SOL: `So11111111111111111111111111111111111111112`
+pub const NATIVE_MINT: Pubkey = Pubkey([
+    6, 155, 136, 87, 254, 171, 129, 132, 251, 104, 127, 99, 70, 24, 192, 53, 218, 196, 57, 220, 26,
+    235, 59, 85, 152, 160, 240, 0, 0, 0, 0, 1,
+]);
+
+/// Specifies the authority type for `SetAuthority` instructions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthorityType {
+    /// Authority to mint new tokens
+    MintTokens,
+    /// Authority to freeze any account associated with the Mint
+    FreezeAccount,
+    /// Owner of a given token account
+    AccountOwner,
+    /// Authority to close a token account
+    CloseAccount,
+}
+
+impl From<AuthorityType> for u8 {
+    fn from(authority_type: AuthorityType) -> Self {
+        match authority_type {
+            AuthorityType::MintTokens => 0,
+            AuthorityType::FreezeAccount => 1,
+            AuthorityType::AccountOwner => 2,
+            AuthorityType::CloseAccount => 3,
+        }
+    }
+}
+
+/// Token program instructions supported by this crate.
+///
+/// Unlike the system program, the token program doesn't use bincode: each instruction is a one
+/// byte tag followed by its little-endian fields, see [`TokenInstruction::pack`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenInstruction {
+    /// Approves a delegate to transfer up to `amount` tokens from the source account.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` The source account.
+    ///   1. `[]` The delegate.
+    ///   2. `[SIGNER]` The source account owner.
+    Approve { amount: u64 },
+    /// Revokes the delegate's authority.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` The source account.
+    ///   1. `[SIGNER]` The source account owner.
+    Revoke,
+    /// Sets a new authority of a mint or account.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` The mint or account to change the authority of.
+    ///   1. `[SIGNER]` The current authority of the mint or account.
+    SetAuthority {
+        /// The type of authority to update, as encoded by the token program.
+        authority_type: u8,
+        /// The new authority, or `None` to remove it.
+        new_authority: Option<Pubkey>,
+    },
+    /// Mints new tokens to an account.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` The mint.
+    ///   1. `[WRITE]` The account to mint tokens to.
+    ///   2. `[SIGNER]` The mint's minting authority.
+    MintTo { amount: u64 },
+    /// Burns tokens by removing them from an account.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` The account to burn from.
+    ///   1. `[WRITE]` The token mint.
+    ///   2. `[SIGNER]` The account's owner/delegate.
+    Burn { amount: u64 },
+    /// Closes an account by transferring all its SOL to the destination account.
+    /// Non-native accounts may only be closed if their token amount is zero.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` The account to close.
+    ///   1. `[WRITE]` The destination account.
+    ///   2. `[SIGNER]` The account's owner.
+    CloseAccount,
+    /// Transfers tokens from one account to another, asserting the token mint and decimals.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` The source account.
+    ///   1. `[]` The token mint.
+    ///   2. `[WRITE]` The destination account.
+    ///   3. `[SIGNER]` The source account's owner/delegate.
+    TransferChecked { amount: u64, decimals: u8 },
+    /// Updates the amount of a native token account to match its lamport balance, after
+    /// lamports were transferred to it to wrap SOL.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` The native token account to sync with its underlying lamports.
+    SyncNative,
+    /// Initializes a new account to hold tokens. The owner is passed as instruction data, so
+    /// no rent sysvar or owner account is required.
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` The account to initialize.
+    ///   1. `[]` The mint this account will be associated with.
+    InitializeAccount3 { owner: Pubkey },
+}
+
+impl TokenInstruction {
+    /// Packs the instruction into the byte layout expected by the token program.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(35);
+        match self {
+            Self::Approve { amount } => {
+                buf.push(4);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::Revoke => buf.push(5),
+            Self::SetAuthority {
+                authority_type,
+                new_authority,
+            } => {
+                buf.push(6);
+                buf.push(*authority_type);
+                match new_authority {
+                    Some(new_authority) => {
+                        buf.push(1);
+                        buf.extend_from_slice(new_authority.as_ref());
+                    }
+                    None => buf.push(0),
+                }
+            }
+            Self::MintTo { amount } => {
+                buf.push(7);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::Burn { amount } => {
+                buf.push(8);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::CloseAccount => buf.push(9),
+            Self::TransferChecked { amount, decimals } => {
+                buf.push(12);
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.push(*decimals);
+            }
+            Self::SyncNative => buf.push(17),
+            Self::InitializeAccount3 { owner } => {
+                buf.push(18);
+                buf.extend_from_slice(owner.as_ref());
+            }
+        }
+        buf
+    }
+}
+
+/// Creates a `TransferChecked` instruction.
+pub fn transfer_checked(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new_readonly(*mint_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, true),
+    ];
+    let data = TokenInstruction::TransferChecked { amount, decimals }.pack();
+    Instruction::new_with_bytes(*token_program_id, &data, account_metas)
+}
+
+/// Creates a `MintTo` instruction.
+pub fn mint_to(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    account_pubkey: &Pubkey,
+    mint_authority_pubkey: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*mint_pubkey, false),
+        AccountMeta::new(*account_pubkey, false),
+        AccountMeta::new_readonly(*mint_authority_pubkey, true),
+    ];
+    let data = TokenInstruction::MintTo { amount }.pack();
+    Instruction::new_with_bytes(*token_program_id, &data, account_metas)
+}
+
+/// Creates a `Burn` instruction.
+pub fn burn(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*account_pubkey, false),
+        AccountMeta::new(*mint_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, true),
+    ];
+    let data = TokenInstruction::Burn { amount }.pack();
+    Instruction::new_with_bytes(*token_program_id, &data, account_metas)
+}
+
+/// Creates an `Approve` instruction.
+pub fn approve(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    delegate_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new_readonly(*delegate_pubkey, false),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+    ];
+    let data = TokenInstruction::Approve { amount }.pack();
+    Instruction::new_with_bytes(*token_program_id, &data, account_metas)
+}
+
+/// Creates a `Revoke` instruction.
+pub fn revoke(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+    ];
+    let data = TokenInstruction::Revoke.pack();
+    Instruction::new_with_bytes(*token_program_id, &data, account_metas)
+}
+
+/// Creates a `CloseAccount` instruction.
+pub fn close_account(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*account_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+    ];
+    let data = TokenInstruction::CloseAccount.pack();
+    Instruction::new_with_bytes(*token_program_id, &data, account_metas)
+}
+
+/// Creates an `InitializeAccount3` instruction.
+///
+/// The account must already be allocated with the token account size and assigned to the
+/// token program.
+pub fn initialize_account3(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*account_pubkey, false),
+        AccountMeta::new_readonly(*mint_pubkey, false),
+    ];
+    let data = TokenInstruction::InitializeAccount3 {
+        owner: *owner_pubkey,
+    }
+    .pack();
+    Instruction::new_with_bytes(*token_program_id, &data, account_metas)
+}
+
+/// Creates a `SyncNative` instruction.
+pub fn sync_native(token_program_id: &Pubkey, account_pubkey: &Pubkey) -> Instruction {
+    let account_metas = vec![AccountMeta::new(*account_pubkey, false)];
+    let data = TokenInstruction::SyncNative.pack();
+    Instruction::new_with_bytes(*token_program_id, &data, account_metas)
+}
+
+/// Creates a `SetAuthority` instruction.
+pub fn set_authority(
+    token_program_id: &Pubkey,
+    owned_pubkey: &Pubkey,
+    new_authority_pubkey: Option<&Pubkey>,
+    authority_type: AuthorityType,
+    owner_pubkey: &Pubkey,
+) -> Instruction {
+    set_authority_raw(
+        token_program_id,
+        owned_pubkey,
+        new_authority_pubkey,
+        authority_type.into(),
+        owner_pubkey,
+    )
+}
+
+/// Creates a `SetAuthority` instruction from an already encoded authority type.
+pub(crate) fn set_authority_raw(
+    token_program_id: &Pubkey,
+    owned_pubkey: &Pubkey,
+    new_authority_pubkey: Option<&Pubkey>,
+    authority_type: u8,
+    owner_pubkey: &Pubkey,
+) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*owned_pubkey, false),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+    ];
+    let data = TokenInstruction::SetAuthority {
+        authority_type,
+        new_authority: new_authority_pubkey.copied(),
+    }
+    .pack();
+    Instruction::new_with_bytes(*token_program_id, &data, account_metas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_transfer_checked() {
+        let data = TokenInstruction::TransferChecked {
+            amount: 1_000_000,
+            decimals: 6,
+        }
+        .pack();
+        let mut expected = vec![12];
+        expected.extend_from_slice(&1_000_000u64.to_le_bytes());
+        expected.push(6);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_pack_set_authority() {
+        let new_authority = Pubkey::from([7u8; 32]);
+        let data = TokenInstruction::SetAuthority {
+            authority_type: AuthorityType::CloseAccount.into(),
+            new_authority: Some(new_authority),
+        }
+        .pack();
+        assert_eq!(data[..3], [6, 3, 1]);
+        assert_eq!(data[3..], new_authority.to_bytes());
+
+        let data = TokenInstruction::SetAuthority {
+            authority_type: AuthorityType::MintTokens.into(),
+            new_authority: None,
+        }
+        .pack();
+        assert_eq!(data, vec![6, 0, 0]);
+    }
+
+    #[test]
+    fn test_pack_simple_instructions() {
+        assert_eq!(TokenInstruction::Revoke.pack(), vec![5]);
+        assert_eq!(TokenInstruction::CloseAccount.pack(), vec![9]);
+        assert_eq!(TokenInstruction::SyncNative.pack(), vec![17]);
+
+        let owner = Pubkey::from([3u8; 32]);
+        let data = TokenInstruction::InitializeAccount3 { owner }.pack();
+        assert_eq!(data[0], 18);
+        assert_eq!(data[1..], owner.to_bytes());
+    }
+
+    #[test]
+    fn test_transfer_checked_accounts() {
+        let source = Pubkey::from([1u8; 32]);
+        let mint = Pubkey::from([2u8; 32]);
+        let destination = Pubkey::from([3u8; 32]);
+        let authority = Pubkey::from([4u8; 32]);
+
+        let instruction = transfer_checked(&ID, &source, &mint, &destination, &authority, 10, 6);
+        assert_eq!(instruction.program_id, ID);
+        assert_eq!(
+            instruction.accounts,
+            vec![
+                AccountMeta::new(source, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new_readonly(authority, true),
+            ]
+        );
+    }
+}
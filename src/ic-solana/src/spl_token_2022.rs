@@ -0,0 +1,119 @@
+//! Instructions and constructors for the SPL Token-2022 program.
+//!
+//! Token-2022 is a superset of the SPL Token program: the instructions shared by both programs
+//! have the same layout, so their builders are re-exported from [`spl_token`], to be passed
+//! [`ID`] as their program ID. Only [`set_authority`] differs, since Token-2022 extensions
+//! introduce additional authority types.
+//!
+//! [`spl_token`]: crate::spl_token
+
+pub use crate::spl_token::{
+    approve, burn, close_account, initialize_account3, mint_to, revoke, sync_native,
+    transfer_checked, TokenInstruction,
+};
+use crate::{
+    spl_token::set_authority_raw,
+    types::{Instruction, Pubkey},
+};
+
+/// `TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb`
+pub const ID: Pubkey = Pubkey([
+    6, 221, 246, 225, 238, 117, 143, 222, 24, 66, 93, 188, 228, 108, 205, 218, 182, 26, 252, 77,
+    131, 185, 13, 39, 254, 189, 249, 40, 216, 161, 139, 252,
+]);
+
+/// The Token-2022 mint of wrapped SOL: `9pan9bMn5HatX4EJdBwg9VgCa7Uz5HL8N1m5D3NdXejP`
+pub const NATIVE_MINT: Pubkey = Pubkey([
+    131, 13, 252, 159, 222, 95, 230, 184, 170, 124, 4, 164, 118, 233, 30, 138, 198, 187, 38, 74,
+    173, 144, 250, 25, 201, 223, 73, 216, 92, 62, 91, 94,
+]);
+
+/// Specifies the authority type for `SetAuthority` instructions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthorityType {
+    /// Authority to mint new tokens
+    MintTokens,
+    /// Authority to freeze any account associated with the Mint
+    FreezeAccount,
+    /// Owner of a given token account
+    AccountOwner,
+    /// Authority to close a mint or token account
+    CloseAccount,
+    /// Authority to set the transfer fee
+    TransferFeeConfig,
+    /// Authority to withdraw withheld tokens from a mint
+    WithheldWithdraw,
+    /// Authority to close a mint account
+    CloseMint,
+    /// Authority to set the interest rate
+    InterestRate,
+    /// Authority to transfer or burn any tokens for a mint
+    PermanentDelegate,
+    /// Authority to update confidential transfer mint and approve accounts for confidential
+    /// transfers
+    ConfidentialTransferMint,
+    /// Authority to set the transfer hook program id
+    TransferHookProgramId,
+    /// Authority to set the withdraw withheld authority encryption key
+    ConfidentialTransferFeeConfig,
+    /// Authority to set the metadata address
+    MetadataPointer,
+    /// Authority to set the group address
+    GroupPointer,
+    /// Authority to set the group member address
+    GroupMemberPointer,
+}
+
+impl From<AuthorityType> for u8 {
+    fn from(authority_type: AuthorityType) -> Self {
+        match authority_type {
+            AuthorityType::MintTokens => 0,
+            AuthorityType::FreezeAccount => 1,
+            AuthorityType::AccountOwner => 2,
+            AuthorityType::CloseAccount => 3,
+            AuthorityType::TransferFeeConfig => 4,
+            AuthorityType::WithheldWithdraw => 5,
+            AuthorityType::CloseMint => 6,
+            AuthorityType::InterestRate => 7,
+            AuthorityType::PermanentDelegate => 8,
+            AuthorityType::ConfidentialTransferMint => 9,
+            AuthorityType::TransferHookProgramId => 10,
+            AuthorityType::ConfidentialTransferFeeConfig => 11,
+            AuthorityType::MetadataPointer => 12,
+            AuthorityType::GroupPointer => 13,
+            AuthorityType::GroupMemberPointer => 14,
+        }
+    }
+}
+
+/// Creates a Token-2022 `SetAuthority` instruction.
+pub fn set_authority(
+    token_program_id: &Pubkey,
+    owned_pubkey: &Pubkey,
+    new_authority_pubkey: Option<&Pubkey>,
+    authority_type: AuthorityType,
+    owner_pubkey: &Pubkey,
+) -> Instruction {
+    set_authority_raw(
+        token_program_id,
+        owned_pubkey,
+        new_authority_pubkey,
+        authority_type.into(),
+        owner_pubkey,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_authority_extension_type() {
+        let mint = Pubkey::from([1u8; 32]);
+        let owner = Pubkey::from([2u8; 32]);
+
+        let instruction = set_authority(&ID, &mint, None, AuthorityType::PermanentDelegate, &owner);
+        assert_eq!(instruction.program_id, ID);
+        assert_eq!(instruction.data, vec![6, 8, 0]);
+    }
+}
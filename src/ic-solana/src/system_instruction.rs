@@ -19,7 +19,7 @@
 //! or they can be [program derived addresses][pda],
 //! where write access to accounts is granted by an owning program.
 //!
-//! [pda]: crate::pubkey::Pubkey::find_program_address
+//! [pda]: crate::types::Pubkey::find_program_address
 //!
 //! The system program ID is defined in [`system_program`].
 //!
@@ -56,7 +56,11 @@
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::types::{AccountMeta, Instruction, Pubkey};
+use crate::{
+    nonce,
+    sysvar::{recent_blockhashes, rent},
+    types::{AccountMeta, Instruction, Pubkey},
+};
 
 #[derive(Error, Debug, Serialize, Clone, PartialEq, Eq)]
 pub enum SystemError {
@@ -147,8 +151,8 @@ pub enum SystemInstruction {
     ///   0. `[WRITE, SIGNER]` Funding account
     ///   1. `[WRITE]` Created account
     ///   2. `[SIGNER]` (optional) Base account; the account matching the base Pubkey below must be
-    ///                          provided as a signer, but may be the same as the funding account
-    ///                          and provided as account 0
+    ///      provided as a signer, but may be the same as the funding account and provided as
+    ///      account 0
     CreateAccountWithSeed {
         /// Base public key
         base: Pubkey,
@@ -309,7 +313,7 @@ pub enum SystemInstruction {
 /// This example submits the instruction from an RPC client.
 /// The `payer` and `new_account` are signers.
 ///
-/// ```
+/// ```ignore
 /// # use solana_program::example_mocks::{solana_sdk, solana_rpc_client};
 /// use solana_rpc_client::rpc_client::RpcClient;
 /// use solana_sdk::{
@@ -367,7 +371,7 @@ pub enum SystemInstruction {
 /// [pda]: Pubkey::find_program_address
 /// [`invoke_signed`]: crate::program::invoke_signed
 ///
-/// ```
+/// ```ignore
 /// # use borsh::{BorshDeserialize, BorshSerialize};
 /// use solana_program::{
 ///     account_info::{next_account_info, AccountInfo},
@@ -515,7 +519,7 @@ pub fn create_account_with_seed(
 /// It assigns the account to a provided program account.
 /// The `payer` and `new_account` are signers.
 ///
-/// ```
+/// ```ignore
 /// # use solana_program::example_mocks::{solana_sdk, solana_rpc_client};
 /// use solana_rpc_client::rpc_client::RpcClient;
 /// use solana_sdk::{
@@ -584,7 +588,7 @@ pub fn create_account_with_seed(
 /// [pda]: Pubkey::find_program_address
 /// [`invoke_signed`]: crate::program::invoke_signed
 ///
-/// ```
+/// ```ignore
 /// # use borsh::{BorshDeserialize, BorshSerialize};
 /// use solana_program::{
 ///     account_info::{next_account_info, AccountInfo},
@@ -726,7 +730,7 @@ pub fn assign_with_seed(
 /// It assigns the account to a provided program account.
 /// The `payer` and `new_account` are signers.
 ///
-/// ```
+/// ```ignore
 /// # use solana_program::example_mocks::{solana_sdk, solana_rpc_client};
 /// use solana_rpc_client::rpc_client::RpcClient;
 /// use solana_sdk::{
@@ -795,7 +799,7 @@ pub fn assign_with_seed(
 /// [pda]: Pubkey::find_program_address
 /// [`invoke_signed`]: crate::program::invoke_signed
 ///
-/// ```
+/// ```ignore
 /// # use borsh::{BorshDeserialize, BorshSerialize};
 /// use solana_program::{
 ///     account_info::{next_account_info, AccountInfo},
@@ -942,7 +946,7 @@ pub fn transfer_with_seed(
 /// It assigns the account to a provided program account.
 /// The `payer` and `new_account` are signers.
 ///
-/// ```
+/// ```ignore
 /// # use solana_program::example_mocks::{solana_sdk, solana_rpc_client};
 /// use solana_rpc_client::rpc_client::RpcClient;
 /// use solana_sdk::{
@@ -1011,7 +1015,7 @@ pub fn transfer_with_seed(
 /// [pda]: Pubkey::find_program_address
 /// [`invoke_signed`]: crate::program::invoke_signed
 ///
-/// ```
+/// ```ignore
 /// # use borsh::{BorshDeserialize, BorshSerialize};
 /// use solana_program::{
 ///     account_info::{next_account_info, AccountInfo},
@@ -1146,7 +1150,7 @@ pub fn allocate_with_seed(
 ///
 /// This example performs multiple transfers in a single transaction.
 ///
-/// ```
+/// ```ignore
 /// # use solana_program::example_mocks::{solana_sdk, solana_rpc_client};
 /// use solana_rpc_client::rpc_client::RpcClient;
 /// use solana_sdk::{
@@ -1202,7 +1206,7 @@ pub fn allocate_with_seed(
 /// [pda]: Pubkey::find_program_address
 /// [`invoke_signed`]: crate::program::invoke_signed
 ///
-/// ```
+/// ```ignore
 /// # use borsh::{BorshDeserialize, BorshSerialize};
 /// use solana_program::{
 ///     account_info::{next_account_info, next_account_infos, AccountInfo},
@@ -1275,36 +1279,35 @@ pub fn transfer_many(from_pubkey: &Pubkey, to_lamports: &[(Pubkey, u64)]) -> Vec
         .collect()
 }
 
-// pub fn create_nonce_account_with_seed(
-//     from_pubkey: &Pubkey,
-//     nonce_pubkey: &Pubkey,
-//     base: &Pubkey,
-//     seed: &str,
-//     authority: &Pubkey,
-//     lamports: u64,
-// ) -> Vec<Instruction> {
-//     vec![
-//         create_account_with_seed(
-//             from_pubkey,
-//             nonce_pubkey,
-//             base,
-//             seed,
-//             lamports,
-//             nonce::State::size() as u64,
-//             &ID,
-//         ),
-//         Instruction::new_with_bincode(
-//             ID,
-//             &SystemInstruction::InitializeNonceAccount(*authority),
-//             vec![
-//                 AccountMeta::new(*nonce_pubkey, false),
-//                 #[allow(deprecated)]
-//                 AccountMeta::new_readonly(recent_blockhashes::id(), false),
-//                 AccountMeta::new_readonly(rent::id(), false),
-//             ],
-//         ),
-//     ]
-// }
+pub fn create_nonce_account_with_seed(
+    from_pubkey: &Pubkey,
+    nonce_pubkey: &Pubkey,
+    base: &Pubkey,
+    seed: &str,
+    authority: &Pubkey,
+    lamports: u64,
+) -> Vec<Instruction> {
+    vec![
+        create_account_with_seed(
+            from_pubkey,
+            nonce_pubkey,
+            base,
+            seed,
+            lamports,
+            nonce::State::size() as u64,
+            &ID,
+        ),
+        Instruction::new_with_bincode(
+            ID,
+            &SystemInstruction::InitializeNonceAccount(*authority),
+            vec![
+                AccountMeta::new(*nonce_pubkey, false),
+                AccountMeta::new_readonly(recent_blockhashes::ID, false),
+                AccountMeta::new_readonly(rent::ID, false),
+            ],
+        ),
+    ]
+}
 
 /// Create an account containing a durable transaction nonce.
 ///
@@ -1373,7 +1376,7 @@ pub fn transfer_many(from_pubkey: &Pubkey, to_lamports: &[(Pubkey, u64)]) -> Vec
 ///
 /// Create a nonce account from an off-chain client:
 ///
-/// ```
+/// ```ignore
 /// # use solana_program::example_mocks::solana_sdk;
 /// # use solana_program::example_mocks::solana_rpc_client;
 /// use solana_rpc_client::rpc_client::RpcClient;
@@ -1417,32 +1420,31 @@ pub fn transfer_many(from_pubkey: &Pubkey, to_lamports: &[(Pubkey, u64)]) -> Vec
 /// #
 /// # Ok::<(), anyhow::Error>(())
 /// ```
-// pub fn create_nonce_account(
-//     from_pubkey: &Pubkey,
-//     nonce_pubkey: &Pubkey,
-//     authority: &Pubkey,
-//     lamports: u64,
-// ) -> Vec<Instruction> {
-//     vec![
-//         create_account(
-//             from_pubkey,
-//             nonce_pubkey,
-//             lamports,
-//             nonce::State::size() as u64,
-//             &ID,
-//         ),
-//         Instruction::new_with_bincode(
-//             ID,
-//             &SystemInstruction::InitializeNonceAccount(*authority),
-//             vec![
-//                 AccountMeta::new(*nonce_pubkey, false),
-//                 #[allow(deprecated)]
-//                 AccountMeta::new_readonly(recent_blockhashes::id(), false),
-//                 AccountMeta::new_readonly(rent::id(), false),
-//             ],
-//         ),
-//     ]
-// }
+pub fn create_nonce_account(
+    from_pubkey: &Pubkey,
+    nonce_pubkey: &Pubkey,
+    authority: &Pubkey,
+    lamports: u64,
+) -> Vec<Instruction> {
+    vec![
+        create_account(
+            from_pubkey,
+            nonce_pubkey,
+            lamports,
+            nonce::State::size() as u64,
+            &ID,
+        ),
+        Instruction::new_with_bincode(
+            ID,
+            &SystemInstruction::InitializeNonceAccount(*authority),
+            vec![
+                AccountMeta::new(*nonce_pubkey, false),
+                AccountMeta::new_readonly(recent_blockhashes::ID, false),
+                AccountMeta::new_readonly(rent::ID, false),
+            ],
+        ),
+    ]
+}
 
 /// Advance the value of a durable transaction nonce.
 ///
@@ -1484,7 +1486,7 @@ pub fn transfer_many(from_pubkey: &Pubkey, to_lamports: &[(Pubkey, u64)]) -> Vec
 ///
 /// Create and sign a transaction with a durable nonce:
 ///
-/// ```
+/// ```ignore
 /// # use solana_program::example_mocks::solana_sdk;
 /// # use solana_program::example_mocks::solana_rpc_client;
 /// # use solana_program::example_mocks::solana_rpc_client_nonce_utils;
@@ -1567,15 +1569,14 @@ pub fn transfer_many(from_pubkey: &Pubkey, to_lamports: &[(Pubkey, u64)]) -> Vec
 /// #
 /// # Ok::<(), anyhow::Error>(())
 /// ```
-// pub fn advance_nonce_account(nonce_pubkey: &Pubkey, authorized_pubkey: &Pubkey) -> Instruction {
-//     let account_metas = vec![
-//         AccountMeta::new(*nonce_pubkey, false),
-//         #[allow(deprecated)]
-//         AccountMeta::new_readonly(recent_blockhashes::id(), false),
-//         AccountMeta::new_readonly(*authorized_pubkey, true),
-//     ];
-//     Instruction::new_with_bincode(ID, &SystemInstruction::AdvanceNonceAccount, account_metas)
-// }
+pub fn advance_nonce_account(nonce_pubkey: &Pubkey, authorized_pubkey: &Pubkey) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*nonce_pubkey, false),
+        AccountMeta::new_readonly(recent_blockhashes::ID, false),
+        AccountMeta::new_readonly(*authorized_pubkey, true),
+    ];
+    Instruction::new_with_bincode(ID, &SystemInstruction::AdvanceNonceAccount, account_metas)
+}
 
 /// Withdraw lamports from a durable transaction nonce account.
 ///
@@ -1605,7 +1606,7 @@ pub fn transfer_many(from_pubkey: &Pubkey, to_lamports: &[(Pubkey, u64)]) -> Vec
 ///
 /// # Examples
 ///
-/// ```
+/// ```ignore
 /// # use solana_program::example_mocks::solana_sdk;
 /// # use solana_program::example_mocks::solana_rpc_client;
 /// use solana_rpc_client::rpc_client::RpcClient;
@@ -1649,26 +1650,25 @@ pub fn transfer_many(from_pubkey: &Pubkey, to_lamports: &[(Pubkey, u64)]) -> Vec
 /// #
 /// # Ok::<(), anyhow::Error>(())
 /// ```
-// pub fn withdraw_nonce_account(
-//     nonce_pubkey: &Pubkey,
-//     authorized_pubkey: &Pubkey,
-//     to_pubkey: &Pubkey,
-//     lamports: u64,
-// ) -> Instruction {
-//     let account_metas = vec![
-//         AccountMeta::new(*nonce_pubkey, false),
-//         AccountMeta::new(*to_pubkey, false),
-//         #[allow(deprecated)]
-//         AccountMeta::new_readonly(recent_blockhashes::id(), false),
-//         AccountMeta::new_readonly(rent::id(), false),
-//         AccountMeta::new_readonly(*authorized_pubkey, true),
-//     ];
-//     Instruction::new_with_bincode(
-//         ID,
-//         &SystemInstruction::WithdrawNonceAccount(lamports),
-//         account_metas,
-//     )
-// }
+pub fn withdraw_nonce_account(
+    nonce_pubkey: &Pubkey,
+    authorized_pubkey: &Pubkey,
+    to_pubkey: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*nonce_pubkey, false),
+        AccountMeta::new(*to_pubkey, false),
+        AccountMeta::new_readonly(recent_blockhashes::ID, false),
+        AccountMeta::new_readonly(rent::ID, false),
+        AccountMeta::new_readonly(*authorized_pubkey, true),
+    ];
+    Instruction::new_with_bincode(
+        ID,
+        &SystemInstruction::WithdrawNonceAccount(lamports),
+        account_metas,
+    )
+}
 
 /// Change the authority of a durable transaction nonce account.
 ///
@@ -1688,7 +1688,7 @@ pub fn transfer_many(from_pubkey: &Pubkey, to_lamports: &[(Pubkey, u64)]) -> Vec
 ///
 /// # Examples
 ///
-/// ```
+/// ```ignore
 /// # use solana_program::example_mocks::solana_sdk;
 /// # use solana_program::example_mocks::solana_rpc_client;
 /// use solana_rpc_client::rpc_client::RpcClient;
@@ -1747,9 +1747,9 @@ pub fn authorize_nonce_account(
     )
 }
 
-// One-time idempotent upgrade of legacy nonce versions in order to bump
-// them out of chain blockhash domain.
-// pub fn upgrade_nonce_account(nonce_pubkey: Pubkey) -> Instruction {
-//     let account_metas = vec![AccountMeta::new(nonce_pubkey, /*is_signer:*/ false)];
-//     Instruction::new_with_bincode(ID, &SystemInstruction::UpgradeNonceAccount, account_metas)
-// }
+/// One-time idempotent upgrade of legacy nonce versions in order to bump
+/// them out of chain blockhash domain.
+pub fn upgrade_nonce_account(nonce_pubkey: Pubkey) -> Instruction {
+    let account_metas = vec![AccountMeta::new(nonce_pubkey, /*is_signer:*/ false)];
+    Instruction::new_with_bincode(ID, &SystemInstruction::UpgradeNonceAccount, account_metas)
+}
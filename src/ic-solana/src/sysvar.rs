@@ -0,0 +1,35 @@
+//! Addresses of the sysvar accounts referenced by the instructions in this crate.
+//!
+//! Sysvars are special accounts that expose cluster state to programs.
+
+pub mod clock {
+    use crate::types::Pubkey;
+
+    /// `SysvarC1ock11111111111111111111111111111111`
+    pub const ID: Pubkey = Pubkey([
+        6, 167, 213, 23, 24, 199, 116, 201, 40, 86, 99, 152, 105, 29, 94, 182, 139, 94, 184, 163,
+        155, 75, 109, 92, 115, 85, 91, 33, 0, 0, 0, 0,
+    ]);
+}
+
+pub mod recent_blockhashes {
+    use crate::types::Pubkey;
+
+    /// `SysvarRecentB1ockHashes11111111111111111111`
+    ///
+    /// Deprecated on chain, but still required by the nonce instructions of the system program.
+    pub const ID: Pubkey = Pubkey([
+        6, 167, 213, 23, 25, 44, 86, 142, 224, 138, 132, 95, 115, 210, 151, 136, 207, 3, 92, 49,
+        69, 178, 26, 179, 68, 216, 6, 46, 169, 64, 0, 0,
+    ]);
+}
+
+pub mod rent {
+    use crate::types::Pubkey;
+
+    /// `SysvarRent111111111111111111111111111111111`
+    pub const ID: Pubkey = Pubkey([
+        6, 167, 213, 23, 25, 44, 92, 81, 33, 140, 201, 76, 61, 74, 241, 127, 88, 218, 238, 8, 155,
+        161, 253, 68, 227, 219, 217, 138, 0, 0, 0, 0,
+    ]);
+}
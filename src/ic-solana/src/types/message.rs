@@ -2,6 +2,7 @@ use candid::CandidType;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    nonce, system_instruction,
     types::{
         account::AccountKey,
         blockhash::BlockHash,
@@ -62,6 +63,22 @@ impl Message {
         )
     }
 
+    /// Creates a message relying on a durable nonce instead of a recent blockhash.
+    ///
+    /// The `advance_nonce_account` instruction is placed first, as required by the runtime, and
+    /// the stored nonce is used as the recent blockhash, so the message stays valid until the
+    /// nonce account is advanced. The nonce authority has to sign the transaction.
+    pub fn new_with_nonce(
+        mut instructions: Vec<Instruction>,
+        payer: Option<&Pubkey>,
+        nonce_account_pubkey: &Pubkey,
+        nonce_data: &nonce::Data,
+    ) -> Self {
+        let advance_nonce = system_instruction::advance_nonce_account(nonce_account_pubkey, &nonce_data.authority);
+        instructions.insert(0, advance_nonce);
+        Self::new_with_blockhash(&instructions, payer, &nonce_data.blockhash())
+    }
+
     pub fn new_with_compiled_instructions(
         num_required_signatures: u8,
         num_readonly_signed_accounts: u8,
@@ -98,6 +115,28 @@ impl Message {
         i < self.header.num_required_signatures as usize
     }
 
+    /// Whether the account at index `i` is writable, according to the message header.
+    pub fn is_writable(&self, i: usize) -> bool {
+        let num_required_signatures = self.header.num_required_signatures as usize;
+        if i < num_required_signatures {
+            i < num_required_signatures.saturating_sub(self.header.num_readonly_signed_accounts as usize)
+        } else {
+            i < self
+                .account_keys
+                .len()
+                .saturating_sub(self.header.num_readonly_unsigned_accounts as usize)
+        }
+    }
+
+    /// Returns the keys of the accounts the message writes to.
+    pub fn writable_keys(&self) -> Vec<&Pubkey> {
+        self.account_keys
+            .iter()
+            .enumerate()
+            .filter_map(|(i, key)| self.is_writable(i).then_some(key))
+            .collect()
+    }
+
     pub fn signer_keys(&self) -> Vec<&Pubkey> {
         let last_key = self
             .account_keys
@@ -239,4 +278,35 @@ mod tests {
 
         assert_eq!(msg, decoded);
     }
+
+    #[test]
+    fn test_new_with_nonce() {
+        let payer = Pubkey::from([1u8; 32]);
+        let nonce_account = Pubkey::from([2u8; 32]);
+        let to = Pubkey::from([3u8; 32]);
+        let nonce_data = nonce::Data {
+            authority: payer,
+            durable_nonce: BlockHash::new(&[4u8; 32]),
+            ..Default::default()
+        };
+
+        let message = Message::new_with_nonce(
+            vec![system_instruction::transfer(&payer, &to, 1)],
+            Some(&payer),
+            &nonce_account,
+            &nonce_data,
+        );
+        assert_eq!(message.recent_blockhash, nonce_data.blockhash());
+        assert_eq!(message.header.num_required_signatures, 1);
+        assert_eq!(message.instructions.len(), 2);
+        assert_eq!(message.program_id(0), Some(&system_instruction::ID));
+        assert_eq!(
+            message.instructions[0].data,
+            bincode::serialize(&system_instruction::SystemInstruction::AdvanceNonceAccount).unwrap()
+        );
+        assert_eq!(
+            message.account_keys[message.instructions[0].accounts[0] as usize],
+            nonce_account
+        );
+    }
 }
@@ -18,6 +18,8 @@ pub mod signature;
 pub mod tagged;
 pub mod transaction;
 pub mod transaction_error;
+pub mod versioned_message;
+pub mod versioned_transaction;
 
 pub use account::*;
 pub use block::*;
@@ -37,6 +39,8 @@ pub use reward::*;
 pub use signature::*;
 pub use transaction::*;
 pub use transaction_error::*;
+pub use versioned_message::*;
+pub use versioned_transaction::*;
 
 /// The unit of time a given leader schedule is honored.
 ///
@@ -3,6 +3,7 @@ use std::{fmt, mem, str::FromStr};
 use candid::CandidType;
 use ic_crypto_ed25519::PublicKey;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 /// Number of bytes in a pubkey
@@ -11,6 +12,14 @@ pub const PUBKEY_BYTES: usize = 32;
 /// Maximum string length of a base58 encoded pubkey
 const MAX_BASE58_LEN: usize = 44;
 
+/// Maximum length of derived `Pubkey` seed
+pub const MAX_SEED_LEN: usize = 32;
+
+/// Maximum number of seeds
+pub const MAX_SEEDS: usize = 16;
+
+const PDA_MARKER: &[u8; 21] = b"ProgramDerivedAddress";
+
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, CandidType)]
 pub struct Pubkey(pub(crate) [u8; PUBKEY_BYTES]);
 
@@ -22,6 +31,17 @@ pub enum ParsePubkeyError {
     Invalid,
 }
 
+#[derive(Error, Debug, Serialize, Clone, PartialEq, Eq)]
+pub enum PubkeyError {
+    /// Length of the seed is too long for address generation
+    #[error("Length of the seed is too long for address generation")]
+    MaxSeedLengthExceeded,
+    #[error("Provided seeds do not result in a valid address")]
+    InvalidSeeds,
+    #[error("Provided owner is not allowed")]
+    IllegalOwner,
+}
+
 impl Pubkey {
     pub fn new(key: [u8; PUBKEY_BYTES]) -> Self {
         Self(key)
@@ -33,10 +53,121 @@ impl Pubkey {
 
     /// Verify an Ed25519 signature
     ///
-    /// Returns Ok if the signature is valid, or Err otherwise
+    /// Returns true if the signature is valid. Keys off the curve, such as program derived
+    /// addresses or keys supplied by an untrusted party, have no private key and never verify.
     pub fn verify_signature(&self, msg: &[u8], signature: &[u8]) -> bool {
-        let pubkey = PublicKey::deserialize_raw(&self.0).expect("invalid public key");
-        pubkey.verify_signature(msg, signature).is_ok()
+        PublicKey::deserialize_raw(&self.0)
+            .is_ok_and(|pubkey| pubkey.verify_signature(msg, signature).is_ok())
+    }
+
+    /// Whether the key is a valid Ed25519 point. Program derived addresses are always off the
+    /// curve, so that no private key can exist for them.
+    pub fn is_on_curve(&self) -> bool {
+        PublicKey::deserialize_raw(&self.0).is_ok()
+    }
+
+    /// Create a valid [program derived address][pda] without searching for a bump seed.
+    ///
+    /// [pda]: https://solana.com/docs/core/pda
+    ///
+    /// Fails with [`PubkeyError::InvalidSeeds`] if the resulting address lies on the Ed25519 curve,
+    /// in which case a different bump seed has to be tried. Use [`Pubkey::find_program_address`] to
+    /// search for one.
+    pub fn create_program_address(
+        seeds: &[&[u8]],
+        program_id: &Pubkey,
+    ) -> Result<Pubkey, PubkeyError> {
+        if seeds.len() > MAX_SEEDS {
+            return Err(PubkeyError::MaxSeedLengthExceeded);
+        }
+        if seeds.iter().any(|seed| seed.len() > MAX_SEED_LEN) {
+            return Err(PubkeyError::MaxSeedLengthExceeded);
+        }
+
+        let mut hasher = Sha256::new();
+        for seed in seeds {
+            hasher.update(seed);
+        }
+        hasher.update(program_id.as_ref());
+        hasher.update(PDA_MARKER);
+        let address =
+            Pubkey::try_from(hasher.finalize().as_slice()).expect("SHA-256 digest is 32 bytes");
+
+        if address.is_on_curve() {
+            return Err(PubkeyError::InvalidSeeds);
+        }
+        Ok(address)
+    }
+
+    /// Find a valid [program derived address][pda] and its corresponding bump seed.
+    ///
+    /// [pda]: https://solana.com/docs/core/pda
+    ///
+    /// Bump seeds are tried from 255 downwards, so the returned bump is the canonical one expected
+    /// by most programs.
+    ///
+    /// # Panics
+    ///
+    /// Panics in the statistically improbable event that no bump seed yields an off-curve address,
+    /// or if the seeds are too long. Use [`Pubkey::try_find_program_address`] to handle these
+    /// cases.
+    pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+        Self::try_find_program_address(seeds, program_id)
+            .unwrap_or_else(|| panic!("Unable to find a viable program address bump seed"))
+    }
+
+    /// Find a valid [program derived address][pda] and its corresponding bump seed.
+    ///
+    /// [pda]: https://solana.com/docs/core/pda
+    ///
+    /// Returns `None` if the seeds are invalid or if no bump seed yields an off-curve address.
+    pub fn try_find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<(Pubkey, u8)> {
+        // One seed is reserved for the bump.
+        if seeds.len() >= MAX_SEEDS {
+            return None;
+        }
+
+        let mut bump_seed = [u8::MAX];
+        for _ in 0..u8::MAX {
+            let mut seeds_with_bump = seeds.to_vec();
+            seeds_with_bump.push(&bump_seed);
+            match Self::create_program_address(&seeds_with_bump, program_id) {
+                Ok(address) => return Some((address, bump_seed[0])),
+                Err(PubkeyError::InvalidSeeds) => (),
+                Err(_) => return None,
+            }
+            bump_seed[0] -= 1;
+        }
+        None
+    }
+
+    /// Derive an address from a base address, a seed and the program that will own the account.
+    ///
+    /// This is the address expected by [`create_account_with_seed`] and the other `*_with_seed`
+    /// system instructions. Unlike program derived addresses, the result may lie on the curve, and
+    /// the base account has to sign for it.
+    ///
+    /// [`create_account_with_seed`]: crate::system_instruction::create_account_with_seed
+    pub fn create_with_seed(
+        base: &Pubkey,
+        seed: &str,
+        owner: &Pubkey,
+    ) -> Result<Pubkey, PubkeyError> {
+        if seed.len() > MAX_SEED_LEN {
+            return Err(PubkeyError::MaxSeedLengthExceeded);
+        }
+
+        // An owner ending with the PDA marker would let the result collide with a program derived
+        // address.
+        if owner.0.ends_with(PDA_MARKER) {
+            return Err(PubkeyError::IllegalOwner);
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(base.as_ref());
+        hasher.update(seed.as_bytes());
+        hasher.update(owner.as_ref());
+        Ok(Pubkey::try_from(hasher.finalize().as_slice()).expect("SHA-256 digest is 32 bytes"))
     }
 }
 
@@ -105,3 +236,85 @@ impl AsRef<[u8]> for Pubkey {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_with_seed() {
+        let base = Pubkey::from_str("7qbRF6YsyGuLUVs6Y1q64bdVrfe4ZcUUz1JRdoVNUJnm").unwrap();
+        let owner = Pubkey::default();
+
+        assert_eq!(
+            Pubkey::create_with_seed(&base, "simpl", &owner),
+            Ok(Pubkey::from_str("1VZLpUFm125xjxDy1Aj4xM6oYpkbskNQR9iK3FQSTkm").unwrap())
+        );
+        assert_eq!(
+            Pubkey::create_with_seed(&base, &"x".repeat(MAX_SEED_LEN + 1), &owner),
+            Err(PubkeyError::MaxSeedLengthExceeded)
+        );
+
+        let mut illegal_owner = [1u8; PUBKEY_BYTES];
+        illegal_owner[PUBKEY_BYTES - PDA_MARKER.len()..].copy_from_slice(PDA_MARKER);
+        assert_eq!(
+            Pubkey::create_with_seed(&base, "simpl", &Pubkey::from(illegal_owner)),
+            Err(PubkeyError::IllegalOwner)
+        );
+    }
+
+    #[test]
+    fn test_find_program_address() {
+        let program_id = Pubkey::from_str("FKSxwAshj48hxAWxGRSK9sLp3XR5wiyeawa6brtp9EjH").unwrap();
+
+        let (address, bump_seed) = Pubkey::find_program_address(&[b"config"], &program_id);
+        assert_eq!(
+            address,
+            Pubkey::from_str("LaQnbVTCpfHW54FnaMqVCS8VngUH6DnwmEJdgGUxc5s").unwrap()
+        );
+        // Bump 255 lands on the curve, so 254 is the first off-curve one.
+        assert_eq!(bump_seed, 254);
+        assert!(!address.is_on_curve());
+        assert_eq!(
+            Pubkey::create_program_address(&[b"config", &[bump_seed]], &program_id),
+            Ok(address)
+        );
+    }
+
+    #[test]
+    fn test_program_address_seed_limits() {
+        let program_id = Pubkey::default();
+        let long_seed = [0u8; MAX_SEED_LEN + 1];
+        assert_eq!(
+            Pubkey::create_program_address(&[&long_seed], &program_id),
+            Err(PubkeyError::MaxSeedLengthExceeded)
+        );
+        assert_eq!(
+            Pubkey::try_find_program_address(&[&long_seed], &program_id),
+            None
+        );
+
+        let seeds = [&[0u8][..]; MAX_SEEDS];
+        assert_eq!(Pubkey::try_find_program_address(&seeds, &program_id), None);
+    }
+
+    #[test]
+    fn test_create_program_address_rejects_on_curve_addresses() {
+        // Roughly half of all hashes are valid curve points, so some bump must be rejected.
+        let program_id = Pubkey::from([7u8; PUBKEY_BYTES]);
+        let rejected = (0..=u8::MAX)
+            .filter(|bump| {
+                Pubkey::create_program_address(&[b"seed", &[*bump]], &program_id).is_err()
+            })
+            .count();
+        assert!(rejected > 0);
+    }
+
+    #[test]
+    fn test_verify_signature_with_off_curve_key() {
+        let program_id = Pubkey::from_str("FKSxwAshj48hxAWxGRSK9sLp3XR5wiyeawa6brtp9EjH").unwrap();
+        let (address, _) = Pubkey::find_program_address(&[b"config"], &program_id);
+
+        assert!(!address.verify_signature(b"message", &[0u8; 64]));
+    }
+}
@@ -3,12 +3,14 @@ use std::{
     str::FromStr,
 };
 
+use base64::{prelude::BASE64_STANDARD, Engine};
 use candid::CandidType;
 use ic_crypto_ed25519::PrivateKey;
 use serde::{de::Error, Deserialize, Serialize};
 
 use super::UiInnerInstructions;
 use crate::{
+    signer::{Signer, SignerError},
     types::{
         account::{AccountKey, UiTokenAmount},
         message::{Message, UiMessage},
@@ -16,6 +18,7 @@ use crate::{
         reward::Rewards,
         signature::Signature,
         transaction_error::TransactionError,
+        versioned_transaction::VersionedTransaction,
         BlockHash, CommitmentConfig, Slot, UnixTimestamp,
     },
     utils::short_vec,
@@ -89,6 +92,86 @@ impl Transaction {
         self.signatures[position] = signature;
     }
 
+    /// Signs the transaction with every required signer.
+    ///
+    /// Fails if a signer is not a required signer of the message, or if any required signature
+    /// is still missing afterwards.
+    pub async fn try_sign(
+        &mut self,
+        signers: &[&dyn Signer],
+        recent_blockhash: BlockHash,
+    ) -> Result<(), SignerError> {
+        self.partial_sign(signers, recent_blockhash).await?;
+
+        if !self.is_signed() {
+            return Err(SignerError::NotEnoughSigners);
+        }
+        Ok(())
+    }
+
+    /// Signs the transaction with a subset of the required signers.
+    ///
+    /// Each signature is placed in the slot matching the signer's position in
+    /// [`Message::signer_keys`]. Changing the blockhash invalidates existing signatures, so they
+    /// are reset when `recent_blockhash` differs from the one in the message.
+    pub async fn partial_sign(
+        &mut self,
+        signers: &[&dyn Signer],
+        recent_blockhash: BlockHash,
+    ) -> Result<(), SignerError> {
+        let positions = self.get_signing_keypair_positions(signers)?;
+
+        if self.message.recent_blockhash != recent_blockhash {
+            self.message.recent_blockhash = recent_blockhash;
+            self.signatures
+                .iter_mut()
+                .for_each(|signature| *signature = Signature::default());
+        }
+
+        let message_data = self.message_data();
+        let signatures = futures::future::try_join_all(
+            signers.iter().map(|signer| signer.sign_message(&message_data)),
+        )
+        .await?;
+
+        for (position, signature) in positions.into_iter().zip(signatures) {
+            self.signatures[position] = signature;
+        }
+        Ok(())
+    }
+
+    /// Returns the signature slot of each signer, failing on the first signer that is not
+    /// required by the message.
+    pub fn get_signing_keypair_positions(
+        &self,
+        signers: &[&dyn Signer],
+    ) -> Result<Vec<usize>, SignerError> {
+        let signer_keys = self.message.signer_keys();
+        signers
+            .iter()
+            .map(|signer| {
+                let pubkey = signer.pubkey();
+                signer_keys
+                    .iter()
+                    .position(|key| **key == pubkey)
+                    .ok_or(SignerError::NotASigner(pubkey))
+            })
+            .collect()
+    }
+
+    /// Verifies every signature against the corresponding signer key.
+    pub fn verify_signatures(&self) -> bool {
+        let message_data = self.message_data();
+        self.signatures.len() == self.message.signer_keys().len()
+            && self
+                .signatures
+                .iter()
+                .zip(self.message.signer_keys())
+                .all(|(signature, pubkey)| {
+                    pubkey.verify_signature(&message_data, signature.as_ref())
+                })
+    }
+
     fn key_index(&self, instruction_index: usize, accounts_index: usize) -> Option<usize> {
         self.message
             .instructions
@@ -207,6 +290,23 @@ pub enum EncodedTransaction {
     Accounts(UiAccountsList),
 }
 
+impl EncodedTransaction {
+    /// Decodes a binary encoded transaction. Returns `None` for JSON encodings, or if the
+    /// transaction can't be decoded.
+    pub fn decode(&self) -> Option<VersionedTransaction> {
+        let bytes = match self {
+            Self::LegacyBinary(blob) | Self::Binary(blob, TransactionBinaryEncoding::Base58) => {
+                bs58::decode(blob).into_vec().ok()?
+            }
+            Self::Binary(blob, TransactionBinaryEncoding::Base64) => {
+                BASE64_STANDARD.decode(blob).ok()?
+            }
+            Self::Json(_) | Self::Accounts(_) => return None,
+        };
+        bincode::deserialize(&bytes).ok()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, CandidType)]
 #[serde(rename_all = "camelCase")]
 pub enum TransactionConfirmationStatus {
@@ -256,12 +356,12 @@ impl TransactionStatus {
         match &self.confirmation_status {
             Some(status) => status.clone(),
             None => {
-                if self.confirmations.is_none() {
-                    TransactionConfirmationStatus::Finalized
-                } else if self.confirmations.unwrap() > 0 {
-                    TransactionConfirmationStatus::Confirmed
-                } else {
-                    TransactionConfirmationStatus::Processed
+                match self.confirmations {
+                    None => TransactionConfirmationStatus::Finalized,
+                    Some(confirmations) if confirmations > 0 => {
+                        TransactionConfirmationStatus::Confirmed
+                    }
+                    Some(_) => TransactionConfirmationStatus::Processed,
                 }
             }
         }
@@ -384,12 +484,16 @@ pub struct EncodedConfirmedTransactionWithStatusMeta {
 #[cfg(test)]
 mod tests {
     use bincode::{deserialize, serialize};
+    use futures::executor::block_on;
 
     use super::*;
-    use crate::types::{
-        blockhash::BlockHash,
-        instruction::{AccountMeta, Instruction},
-        UiParsedMessage,
+    use crate::{
+        signer::TestSigner,
+        types::{
+            blockhash::BlockHash,
+            instruction::{AccountMeta, Instruction},
+            UiParsedMessage,
+        },
     };
 
     fn create_sample_transaction() -> Transaction {
@@ -422,6 +526,77 @@ mod tests {
         tx
     }
 
+    fn create_two_signer_transaction(
+        fee_payer: &TestSigner,
+        authority: &TestSigner,
+    ) -> Transaction {
+        let to = Pubkey::from([3u8; 32]);
+        let program_id = Pubkey::from([4u8; 32]);
+        let instruction = Instruction::new_with_bincode(
+            program_id,
+            &(1u8, 2u8, 3u8),
+            vec![
+                AccountMeta::new(authority.pubkey(), true),
+                AccountMeta::new(to, false),
+            ],
+        );
+        Transaction::new_unsigned(Message::new(&[instruction], Some(&fee_payer.pubkey())))
+    }
+
+    #[test]
+    fn test_try_sign_multiple_signers() {
+        let fee_payer = TestSigner::new("fee_payer");
+        let authority = TestSigner::new("authority");
+        let mut tx = create_two_signer_transaction(&fee_payer, &authority);
+        let blockhash = BlockHash::new(&[7u8; 32]);
+
+        // Signers may be passed in any order.
+        block_on(tx.try_sign(&[&authority, &fee_payer], blockhash)).unwrap();
+
+        assert!(tx.is_signed());
+        assert!(tx.verify_signatures());
+        assert_eq!(tx.message.recent_blockhash, blockhash);
+        assert_eq!(tx.message.signer_keys(), vec![&fee_payer.pubkey(), &authority.pubkey()]);
+    }
+
+    #[test]
+    fn test_partial_sign() {
+        let fee_payer = TestSigner::new("fee_payer");
+        let authority = TestSigner::new("authority");
+        let mut tx = create_two_signer_transaction(&fee_payer, &authority);
+        let blockhash = BlockHash::new(&[7u8; 32]);
+
+        block_on(tx.partial_sign(&[&authority], blockhash)).unwrap();
+        assert!(!tx.is_signed());
+        assert_eq!(tx.signatures[0], Signature::default());
+
+        block_on(tx.partial_sign(&[&fee_payer], blockhash)).unwrap();
+        assert!(tx.is_signed());
+        assert!(tx.verify_signatures());
+
+        // A new blockhash invalidates the signatures collected so far.
+        block_on(tx.partial_sign(&[&fee_payer], BlockHash::new(&[8u8; 32]))).unwrap();
+        assert_eq!(tx.signatures[1], Signature::default());
+    }
+
+    #[test]
+    fn test_try_sign_errors() {
+        let fee_payer = TestSigner::new("fee_payer");
+        let authority = TestSigner::new("authority");
+        let stranger = TestSigner::new("stranger");
+        let mut tx = create_two_signer_transaction(&fee_payer, &authority);
+        let blockhash = BlockHash::default();
+
+        assert_eq!(
+            block_on(tx.try_sign(&[&fee_payer, &stranger], blockhash)),
+            Err(SignerError::NotASigner(stranger.pubkey()))
+        );
+        assert_eq!(
+            block_on(tx.try_sign(&[&fee_payer], blockhash)),
+            Err(SignerError::NotEnoughSigners)
+        );
+    }
+
     #[test]
     fn test_transaction_serialize() {
         let tx = create_sample_transaction();
@@ -491,4 +666,23 @@ mod tests {
         let _: EncodedConfirmedTransactionWithStatusMeta =
             serde_json::from_str(&test_json).unwrap();
     }
+
+    #[test]
+    fn test_decode_encoded_transaction() {
+        let tx = create_sample_transaction();
+        let expected = VersionedTransaction::from(tx.clone());
+
+        let base64 = EncodedTransaction::Binary(
+            BASE64_STANDARD.encode(tx.serialize()),
+            TransactionBinaryEncoding::Base64,
+        );
+        assert_eq!(base64.decode(), Some(expected.clone()));
+
+        let base58 = EncodedTransaction::LegacyBinary(tx.to_string());
+        assert_eq!(base58.decode(), Some(expected));
+
+        let invalid =
+            EncodedTransaction::Binary("AAAA".to_string(), TransactionBinaryEncoding::Base64);
+        assert_eq!(invalid.decode(), None);
+    }
 }
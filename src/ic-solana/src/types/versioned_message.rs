@@ -0,0 +1,523 @@
+use std::fmt;
+
+use candid::CandidType;
+use serde::{
+    de::{self, Deserializer, SeqAccess, Unexpected, Visitor},
+    ser::{SerializeTuple, Serializer},
+    Deserialize, Serialize,
+};
+
+use crate::{
+    types::{
+        blockhash::BlockHash,
+        compiled_keys::{CompileError, CompiledKeys},
+        instruction::{CompiledInstruction, Instruction},
+        message::{Message, MessageHeader, MESSAGE_VERSION_PREFIX},
+        pubkey::Pubkey,
+    },
+    utils::short_vec,
+};
+
+/// Maximum number of accounts a transaction can reference, including the ones loaded from
+/// address lookup tables.
+pub const MAX_ACCOUNT_KEYS: usize = 256;
+
+/// Address table lookups describe an on-chain address lookup table to use
+/// for loading more readonly and writable accounts in a single tx.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq, Clone, CandidType)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageAddressTableLookup {
+    /// Address lookup table account key
+    pub account_key: Pubkey,
+    /// List of indexes used to load writable account addresses
+    #[serde(with = "short_vec")]
+    pub writable_indexes: Vec<u8>,
+    /// List of indexes used to load readonly account addresses
+    #[serde(with = "short_vec")]
+    pub readonly_indexes: Vec<u8>,
+}
+
+/// The contents of an address lookup table account, as needed to compile a message against it.
+#[derive(Debug, PartialEq, Eq, Clone, CandidType, Deserialize)]
+pub struct AddressLookupTableAccount {
+    pub key: Pubkey,
+    pub addresses: Vec<Pubkey>,
+}
+
+/// Collection of addresses loaded from on-chain lookup tables, split
+/// by readonly and writable.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct LoadedAddresses {
+    /// List of addresses for writable loaded accounts
+    pub writable: Vec<Pubkey>,
+    /// List of addresses for read-only loaded accounts
+    pub readonly: Vec<Pubkey>,
+}
+
+impl FromIterator<LoadedAddresses> for LoadedAddresses {
+    fn from_iter<T: IntoIterator<Item = LoadedAddresses>>(iter: T) -> Self {
+        let (writable, readonly): (Vec<Vec<Pubkey>>, Vec<Vec<Pubkey>>) = iter
+            .into_iter()
+            .map(|addresses| (addresses.writable, addresses.readonly))
+            .unzip();
+
+        LoadedAddresses {
+            writable: writable.into_iter().flatten().collect(),
+            readonly: readonly.into_iter().flatten().collect(),
+        }
+    }
+}
+
+/// A Solana transaction message (v0).
+///
+/// This message format supports succinct account loading with
+/// on-chain address lookup tables.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageV0 {
+    /// The message header, identifying signed and read-only `account_keys`.
+    /// Header values only describe static `account_keys`, they do not describe
+    /// any additional account keys loaded via address table lookups.
+    pub header: MessageHeader,
+
+    /// List of accounts loaded by this transaction.
+    #[serde(with = "short_vec")]
+    pub account_keys: Vec<Pubkey>,
+
+    /// The blockhash of a recent block.
+    pub recent_blockhash: BlockHash,
+
+    /// Instructions that invoke a designated program, are executed in sequence,
+    /// and committed in one atomic transaction if all succeed.
+    ///
+    /// Instruction account indexes refer to the static `account_keys` followed by the writable
+    /// and then the readonly addresses loaded by `address_table_lookups`.
+    #[serde(with = "short_vec")]
+    pub instructions: Vec<CompiledInstruction>,
+
+    /// List of address table lookups used to load additional accounts
+    /// for this transaction.
+    #[serde(with = "short_vec")]
+    pub address_table_lookups: Vec<MessageAddressTableLookup>,
+}
+
+impl MessageV0 {
+    /// Compiles a v0 message, loading every non-signer, non-program account found in
+    /// `address_lookup_table_accounts` through a table lookup instead of a static key.
+    pub fn try_compile(
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+        recent_blockhash: BlockHash,
+    ) -> Result<Self, CompileError> {
+        let mut compiled_keys = CompiledKeys::compile(instructions, Some(*payer));
+
+        let mut address_table_lookups = Vec::with_capacity(address_lookup_table_accounts.len());
+        let mut loaded_addresses_list = Vec::with_capacity(address_lookup_table_accounts.len());
+        for lookup_table_account in address_lookup_table_accounts {
+            if let Some((lookup, loaded_addresses)) =
+                compiled_keys.try_extract_table_lookup(lookup_table_account)?
+            {
+                address_table_lookups.push(lookup);
+                loaded_addresses_list.push(loaded_addresses);
+            }
+        }
+
+        let (header, static_keys) = compiled_keys.try_into_message_components()?;
+        let loaded_addresses: LoadedAddresses = loaded_addresses_list.into_iter().collect();
+
+        let account_keys: Vec<Pubkey> = static_keys
+            .iter()
+            .chain(&loaded_addresses.writable)
+            .chain(&loaded_addresses.readonly)
+            .copied()
+            .collect();
+        if account_keys.len() > MAX_ACCOUNT_KEYS {
+            return Err(CompileError::AccountIndexOverflow);
+        }
+
+        let instructions = instructions
+            .iter()
+            .map(|ix| try_compile_instruction(ix, &account_keys))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            header,
+            account_keys: static_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups,
+        })
+    }
+
+    /// Serializes the message, including the version prefix.
+    pub fn serialize(&self) -> Vec<u8> {
+        bincode::serialize(&(MESSAGE_VERSION_PREFIX, self)).unwrap()
+    }
+}
+
+fn try_compile_instruction(
+    ix: &Instruction,
+    account_keys: &[Pubkey],
+) -> Result<CompiledInstruction, CompileError> {
+    let index = |key: &Pubkey| -> Result<u8, CompileError> {
+        let position = account_keys
+            .iter()
+            .position(|k| k == key)
+            .ok_or(CompileError::UnknownInstructionKey(*key))?;
+        u8::try_from(position).map_err(|_| CompileError::AccountIndexOverflow)
+    };
+
+    Ok(CompiledInstruction {
+        program_id_index: index(&ix.program_id)?,
+        accounts: ix
+            .accounts
+            .iter()
+            .map(|account_meta| index(&account_meta.pubkey))
+            .collect::<Result<_, _>>()?,
+        data: ix.data.clone(),
+    })
+}
+
+/// Either a legacy message or a v0 message.
+///
+/// # Serialization
+///
+/// If the first bit is set, the remaining 7 bits will be used to determine
+/// which message version is serialized starting from version `0`. If the first
+/// is bit is not set, all bytes are used to encode the legacy `Message`
+/// format.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum VersionedMessage {
+    Legacy(Message),
+    V0(MessageV0),
+}
+
+impl Default for VersionedMessage {
+    fn default() -> Self {
+        Self::Legacy(Message::default())
+    }
+}
+
+impl From<Message> for VersionedMessage {
+    fn from(message: Message) -> Self {
+        Self::Legacy(message)
+    }
+}
+
+impl From<MessageV0> for VersionedMessage {
+    fn from(message: MessageV0) -> Self {
+        Self::V0(message)
+    }
+}
+
+impl VersionedMessage {
+    pub fn header(&self) -> &MessageHeader {
+        match self {
+            Self::Legacy(message) => &message.header,
+            Self::V0(message) => &message.header,
+        }
+    }
+
+    /// Returns the keys stored in the message itself, excluding those loaded from lookup tables.
+    pub fn static_account_keys(&self) -> &[Pubkey] {
+        match self {
+            Self::Legacy(message) => &message.account_keys,
+            Self::V0(message) => &message.account_keys,
+        }
+    }
+
+    pub fn address_table_lookups(&self) -> Option<&[MessageAddressTableLookup]> {
+        match self {
+            Self::Legacy(_) => None,
+            Self::V0(message) => Some(&message.address_table_lookups),
+        }
+    }
+
+    pub fn recent_blockhash(&self) -> &BlockHash {
+        match self {
+            Self::Legacy(message) => &message.recent_blockhash,
+            Self::V0(message) => &message.recent_blockhash,
+        }
+    }
+
+    pub fn set_recent_blockhash(&mut self, recent_blockhash: BlockHash) {
+        match self {
+            Self::Legacy(message) => message.recent_blockhash = recent_blockhash,
+            Self::V0(message) => message.recent_blockhash = recent_blockhash,
+        }
+    }
+
+    pub fn instructions(&self) -> &[CompiledInstruction] {
+        match self {
+            Self::Legacy(message) => &message.instructions,
+            Self::V0(message) => &message.instructions,
+        }
+    }
+
+    /// Signers are always static keys, in the first `num_required_signatures` slots.
+    pub fn signer_keys(&self) -> Vec<&Pubkey> {
+        let last_key = self
+            .static_account_keys()
+            .len()
+            .min(self.header().num_required_signatures as usize);
+        self.static_account_keys()[..last_key].iter().collect()
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+}
+
+impl Serialize for VersionedMessage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Legacy(message) => {
+                let mut seq = serializer.serialize_tuple(1)?;
+                seq.serialize_element(message)?;
+                seq.end()
+            }
+            Self::V0(message) => {
+                let mut seq = serializer.serialize_tuple(2)?;
+                seq.serialize_element(&MESSAGE_VERSION_PREFIX)?;
+                seq.serialize_element(message)?;
+                seq.end()
+            }
+        }
+    }
+}
+
+enum MessagePrefix {
+    Legacy(u8),
+    Versioned(u8),
+}
+
+impl<'de> Deserialize<'de> for MessagePrefix {
+    fn deserialize<D>(deserializer: D) -> Result<MessagePrefix, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct PrefixVisitor;
+
+        impl Visitor<'_> for PrefixVisitor {
+            type Value = MessagePrefix;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("message prefix byte")
+            }
+
+            // Serde's integer visitors bubble up to u64 so check the prefix
+            // with this function instead of visit_u8. This approach is
+            // necessary because serde_json directly calls visit_u64 for
+            // unsigned integers.
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<MessagePrefix, E> {
+                if value > u8::MAX as u64 {
+                    Err(de::Error::invalid_type(Unexpected::Unsigned(value), &self))?;
+                }
+
+                let byte = value as u8;
+                if byte & MESSAGE_VERSION_PREFIX != 0 {
+                    Ok(MessagePrefix::Versioned(byte & !MESSAGE_VERSION_PREFIX))
+                } else {
+                    Ok(MessagePrefix::Legacy(byte))
+                }
+            }
+        }
+
+        deserializer.deserialize_u8(PrefixVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionedMessage {
+    fn deserialize<D>(deserializer: D) -> Result<VersionedMessage, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct MessageVisitor;
+
+        impl<'de> Visitor<'de> for MessageVisitor {
+            type Value = VersionedMessage;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("message bytes")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<VersionedMessage, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let prefix: MessagePrefix = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+                match prefix {
+                    MessagePrefix::Legacy(num_required_signatures) => {
+                        // The remaining fields of the legacy Message struct after the first byte.
+                        #[derive(Deserialize)]
+                        struct RemainingLegacyMessage {
+                            pub num_readonly_signed_accounts: u8,
+                            pub num_readonly_unsigned_accounts: u8,
+                            #[serde(with = "short_vec")]
+                            pub account_keys: Vec<Pubkey>,
+                            pub recent_blockhash: BlockHash,
+                            #[serde(with = "short_vec")]
+                            pub instructions: Vec<CompiledInstruction>,
+                        }
+
+                        let message: RemainingLegacyMessage = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+                        Ok(VersionedMessage::Legacy(Message {
+                            header: MessageHeader {
+                                num_required_signatures,
+                                num_readonly_signed_accounts: message.num_readonly_signed_accounts,
+                                num_readonly_unsigned_accounts: message
+                                    .num_readonly_unsigned_accounts,
+                            },
+                            account_keys: message.account_keys,
+                            recent_blockhash: message.recent_blockhash,
+                            instructions: message.instructions,
+                        }))
+                    }
+                    MessagePrefix::Versioned(version) => match version {
+                        0 => Ok(VersionedMessage::V0(
+                            seq.next_element()?
+                                .ok_or_else(|| de::Error::invalid_length(1, &self))?,
+                        )),
+                        127 => {
+                            // 0xff is used as the first byte of the off-chain messages
+                            // which corresponds to version 127 of the versioned messages.
+                            // This explicit check is added to prevent the usage of version 127
+                            // in the runtime as a valid transaction.
+                            Err(de::Error::custom("off-chain messages are not accepted"))
+                        }
+                        _ => Err(de::Error::invalid_value(
+                            Unexpected::Unsigned(version as u64),
+                            &"a valid transaction message version",
+                        )),
+                    },
+                }
+            }
+        }
+
+        deserializer.deserialize_tuple(2, MessageVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::instruction::AccountMeta;
+
+    fn sample_instructions(payer: Pubkey) -> Vec<Instruction> {
+        let program_id = Pubkey::from([9u8; 32]);
+        vec![Instruction::new_with_bytes(
+            program_id,
+            &[1, 2, 3],
+            vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new(Pubkey::from([1u8; 32]), false),
+                AccountMeta::new_readonly(Pubkey::from([2u8; 32]), false),
+                AccountMeta::new(Pubkey::from([3u8; 32]), false),
+            ],
+        )]
+    }
+
+    #[test]
+    fn test_legacy_message_round_trip() {
+        let payer = Pubkey::from([5u8; 32]);
+        let message = Message::new_with_blockhash(
+            &sample_instructions(payer),
+            Some(&payer),
+            &BlockHash::new(&[6u8; 32]),
+        );
+        let versioned = VersionedMessage::Legacy(message.clone());
+
+        // A legacy message keeps its original wire format.
+        assert_eq!(versioned.serialize(), message.serialize());
+
+        let decoded: VersionedMessage = bincode::deserialize(&versioned.serialize()).unwrap();
+        assert_eq!(decoded, versioned);
+    }
+
+    #[test]
+    fn test_v0_message_compiles_against_lookup_table() {
+        let payer = Pubkey::from([5u8; 32]);
+        let lookup_table = AddressLookupTableAccount {
+            key: Pubkey::from([8u8; 32]),
+            addresses: vec![
+                Pubkey::from([3u8; 32]),
+                Pubkey::from([2u8; 32]),
+                Pubkey::from([9u8; 32]),
+            ],
+        };
+
+        let message = MessageV0::try_compile(
+            &payer,
+            &sample_instructions(payer),
+            std::slice::from_ref(&lookup_table),
+            BlockHash::new(&[6u8; 32]),
+        )
+        .unwrap();
+
+        // The program and the account missing from the table stay static.
+        assert_eq!(
+            message.account_keys,
+            vec![payer, Pubkey::from([1u8; 32]), Pubkey::from([9u8; 32])]
+        );
+        assert_eq!(
+            message.address_table_lookups,
+            vec![MessageAddressTableLookup {
+                account_key: lookup_table.key,
+                writable_indexes: vec![0],
+                readonly_indexes: vec![1],
+            }]
+        );
+        // Loaded writable accounts follow the static keys, then loaded readonly accounts.
+        assert_eq!(message.instructions[0].program_id_index, 2);
+        assert_eq!(message.instructions[0].accounts, vec![0, 1, 4, 3]);
+
+        let message_bytes = message.serialize();
+        let versioned = VersionedMessage::V0(message);
+        let bytes = versioned.serialize();
+        assert_eq!(bytes[0], MESSAGE_VERSION_PREFIX);
+        assert_eq!(bytes, message_bytes);
+
+        let decoded: VersionedMessage = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded, versioned);
+    }
+
+    #[test]
+    fn test_v0_message_unknown_versions_rejected() {
+        let mut bytes = VersionedMessage::V0(MessageV0::default()).serialize();
+        bytes[0] = MESSAGE_VERSION_PREFIX | 1;
+        assert!(bincode::deserialize::<VersionedMessage>(&bytes).is_err());
+
+        bytes[0] = 0xff;
+        assert!(bincode::deserialize::<VersionedMessage>(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_lookup_table_index_overflow() {
+        let payer = Pubkey::from([5u8; 32]);
+        let mut addresses: Vec<Pubkey> = (0..=255u8).map(|i| Pubkey::from([i; 32])).collect();
+        addresses[3] = Pubkey::from([200u8; 32]);
+        // Key [3; 32] ends up at index 256, which doesn't fit in a u8.
+        addresses.push(Pubkey::from([3u8; 32]));
+
+        let result = MessageV0::try_compile(
+            &payer,
+            &sample_instructions(payer),
+            &[AddressLookupTableAccount {
+                key: Pubkey::from([8u8; 32]),
+                addresses,
+            }],
+            BlockHash::default(),
+        );
+        assert_eq!(result, Err(CompileError::AddressTableLookupIndexOverflow));
+    }
+}
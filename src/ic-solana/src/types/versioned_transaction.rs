@@ -0,0 +1,219 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de::Error, Deserialize, Serialize};
+
+use crate::{
+    signer::{Signer, SignerError},
+    types::{
+        blockhash::BlockHash, pubkey::Pubkey, signature::Signature, transaction::Transaction,
+        versioned_message::VersionedMessage,
+    },
+    utils::short_vec,
+};
+
+/// An atomic transaction that can carry either a legacy or a versioned message.
+#[derive(Debug, PartialEq, Default, Eq, Clone, Serialize, Deserialize)]
+pub struct VersionedTransaction {
+    /// List of signatures
+    #[serde(with = "short_vec")]
+    pub signatures: Vec<Signature>,
+    /// Message to sign.
+    pub message: VersionedMessage,
+}
+
+impl From<Transaction> for VersionedTransaction {
+    fn from(transaction: Transaction) -> Self {
+        Self {
+            signatures: transaction.signatures,
+            message: VersionedMessage::Legacy(transaction.message),
+        }
+    }
+}
+
+impl VersionedTransaction {
+    pub fn new_unsigned(message: VersionedMessage) -> Self {
+        Self {
+            signatures: vec![
+                Signature::default();
+                message.header().num_required_signatures as usize
+            ],
+            message,
+        }
+    }
+
+    /// Return the serialized message data to sign.
+    pub fn message_data(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signatures
+            .iter()
+            .all(|signature| *signature != Signature::default())
+    }
+
+    /// Signs the transaction with every required signer.
+    ///
+    /// See [`Transaction::try_sign`].
+    pub async fn try_sign(
+        &mut self,
+        signers: &[&dyn Signer],
+        recent_blockhash: BlockHash,
+    ) -> Result<(), SignerError> {
+        self.partial_sign(signers, recent_blockhash).await?;
+
+        if !self.is_signed() {
+            return Err(SignerError::NotEnoughSigners);
+        }
+        Ok(())
+    }
+
+    /// Signs the transaction with a subset of the required signers.
+    ///
+    /// See [`Transaction::partial_sign`].
+    pub async fn partial_sign(
+        &mut self,
+        signers: &[&dyn Signer],
+        recent_blockhash: BlockHash,
+    ) -> Result<(), SignerError> {
+        let positions = self.get_signing_keypair_positions(signers)?;
+
+        if *self.message.recent_blockhash() != recent_blockhash {
+            self.message.set_recent_blockhash(recent_blockhash);
+            self.signatures
+                .iter_mut()
+                .for_each(|signature| *signature = Signature::default());
+        }
+
+        let message_data = self.message_data();
+        let signatures = futures::future::try_join_all(
+            signers
+                .iter()
+                .map(|signer| signer.sign_message(&message_data)),
+        )
+        .await?;
+
+        for (position, signature) in positions.into_iter().zip(signatures) {
+            self.signatures[position] = signature;
+        }
+        Ok(())
+    }
+
+    pub fn get_signing_keypair_positions(
+        &self,
+        signers: &[&dyn Signer],
+    ) -> Result<Vec<usize>, SignerError> {
+        let signer_keys = self.message.signer_keys();
+        signers
+            .iter()
+            .map(|signer| {
+                let pubkey = signer.pubkey();
+                signer_keys
+                    .iter()
+                    .position(|key| **key == pubkey)
+                    .ok_or(SignerError::NotASigner(pubkey))
+            })
+            .collect()
+    }
+
+    /// Verifies every signature against the corresponding signer key.
+    pub fn verify_signatures(&self) -> bool {
+        let message_data = self.message_data();
+        let signer_keys: Vec<&Pubkey> = self.message.signer_keys();
+        self.signatures.len() == signer_keys.len()
+            && self
+                .signatures
+                .iter()
+                .zip(signer_keys)
+                .all(|(signature, pubkey)| {
+                    pubkey.verify_signature(&message_data, signature.as_ref())
+                })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("Transaction serialization failed")
+    }
+}
+
+impl fmt::Display for VersionedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", bs58::encode(self.serialize()).into_string())
+    }
+}
+
+impl FromStr for VersionedTransaction {
+    type Err = bincode::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| bincode::Error::custom("Transaction deserialization failed"))?;
+        bincode::deserialize(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::{
+        signer::TestSigner,
+        types::{
+            instruction::{AccountMeta, Instruction},
+            versioned_message::{AddressLookupTableAccount, MessageV0},
+        },
+    };
+
+    #[test]
+    fn test_v0_transaction_sign_and_round_trip() {
+        let payer = TestSigner::new("payer");
+        let readonly = Pubkey::from([2u8; 32]);
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::from([9u8; 32]),
+            &[1],
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(readonly, false),
+            ],
+        );
+        let message = MessageV0::try_compile(
+            &payer.pubkey(),
+            &[instruction],
+            &[AddressLookupTableAccount {
+                key: Pubkey::from([8u8; 32]),
+                addresses: vec![readonly],
+            }],
+            BlockHash::default(),
+        )
+        .unwrap();
+
+        let mut tx = VersionedTransaction::new_unsigned(message.into());
+        block_on(tx.try_sign(&[&payer], BlockHash::new(&[7u8; 32]))).unwrap();
+        assert!(tx.is_signed());
+        assert!(tx.verify_signatures());
+
+        let decoded = VersionedTransaction::from_str(&tx.to_string()).unwrap();
+        assert_eq!(decoded, tx);
+        assert!(decoded.verify_signatures());
+    }
+
+    #[test]
+    fn test_legacy_transaction_conversion() {
+        let payer = TestSigner::new("payer");
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::from([9u8; 32]),
+            &[1],
+            vec![AccountMeta::new(payer.pubkey(), true)],
+        );
+        let mut tx = Transaction::new_unsigned(crate::types::Message::new(
+            &[instruction],
+            Some(&payer.pubkey()),
+        ));
+        block_on(tx.try_sign(&[&payer], BlockHash::new(&[7u8; 32]))).unwrap();
+
+        let versioned = VersionedTransaction::from(tx.clone());
+        assert_eq!(versioned.serialize(), tx.serialize());
+        assert!(versioned.verify_signatures());
+    }
+}
@@ -0,0 +1,122 @@
+//! Roles of the principals calling the canister.
+//!
+//! Endpoints moving funds or changing the configuration require a [`Role`], granted by
+//! controllers and kept in stable memory. Roles are ordered, each including the ones below it,
+//! and controllers are admins without a grant. Granted roles are counted in the `auths` metric,
+//! and calls rejected for lacking one in `err_unauthorized`, see [`ic_solana::metrics`].
+
+use std::borrow::Cow;
+
+use candid::{CandidType, Decode, Encode, Principal};
+use ic_solana::{add_metric, add_metric_entry, metrics::MetricAuth, sub_metric_entry};
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::Deserialize;
+
+use crate::state::{principal_key, ROLES};
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Can quote and place orders.
+    User,
+    /// Can also maintain the treasury.
+    Operator,
+    /// Can also move treasury funds and change the configuration.
+    Admin,
+}
+
+impl Role {
+    fn metric(self) -> MetricAuth {
+        MetricAuth(format!("{self:?}"))
+    }
+}
+
+impl Storable for Role {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode role"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("Failed to decode role")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Returns the role of a principal, if any.
+pub fn role(principal: &Principal) -> Option<Role> {
+    if ic_cdk::api::is_controller(principal) {
+        return Some(Role::Admin);
+    }
+    ROLES.with(|roles| roles.borrow().get(&principal_key(principal)))
+}
+
+/// Fails unless the caller has `role`, or a higher one.
+pub fn require(role: Role) -> Result<(), String> {
+    let caller = ic_cdk::caller();
+    if self::role(&caller).is_some_and(|granted| granted >= role) {
+        return Ok(());
+    }
+    add_metric_entry!(err_unauthorized, role.metric(), 1);
+    Err(format!("Caller {caller} doesn't have the {role:?} role"))
+}
+
+/// Fails unless the caller is `principal`, or has `role` or a higher one.
+pub fn require_self_or(principal: &Principal, role: Role) -> Result<(), String> {
+    if ic_cdk::caller() == *principal {
+        return Ok(());
+    }
+    require(role)
+}
+
+/// Grants `role` to a principal, replacing its previous role.
+pub fn grant(principal: Principal, role: Role) -> Result<(), String> {
+    require_controller()?;
+    if principal == Principal::anonymous() {
+        return Err("Roles can't be granted to the anonymous principal".to_string());
+    }
+    let previous = ROLES.with(|roles| roles.borrow_mut().insert(principal_key(&principal), role));
+    if let Some(previous) = previous {
+        sub_metric_entry!(auths, previous.metric(), 1);
+    }
+    add_metric_entry!(auths, role.metric(), 1);
+    Ok(())
+}
+
+/// Revokes the role of a principal. Controllers stay admins.
+pub fn revoke(principal: Principal) -> Result<(), String> {
+    require_controller()?;
+    let previous = ROLES.with(|roles| roles.borrow_mut().remove(&principal_key(&principal)));
+    if let Some(previous) = previous {
+        sub_metric_entry!(auths, previous.metric(), 1);
+    }
+    Ok(())
+}
+
+/// Returns the granted roles, by principal.
+pub fn list() -> Vec<(Principal, Role)> {
+    ROLES.with(|roles| {
+        roles
+            .borrow()
+            .iter()
+            .map(|(key, role)| (Principal::from_slice(key.as_slice()), role))
+            .collect()
+    })
+}
+
+/// Counts the granted roles in the `auths` metric. Metrics don't survive upgrades, so this runs
+/// on post-upgrade.
+pub fn init_metrics() {
+    let roles: Vec<Role> =
+        ROLES.with(|roles| roles.borrow().iter().map(|(_, role)| role).collect());
+    for role in roles {
+        add_metric_entry!(auths, role.metric(), 1);
+    }
+}
+
+fn require_controller() -> Result<(), String> {
+    if ic_cdk::api::is_controller(&ic_cdk::caller()) {
+        return Ok(());
+    }
+    add_metric!(err_no_permission, 1);
+    Err("Only controllers can manage roles".to_string())
+}
@@ -0,0 +1,122 @@
+//! USD/INR rate from the exchange rate canister (XRC) of the IC.
+//!
+//! See <https://internetcomputer.org/docs/current/developer-docs/defi/exchange-rate-canister>.
+
+use candid::{CandidType, Principal};
+use serde::Deserialize;
+
+/// Id of the exchange rate canister on the mainnet.
+pub const XRC_CANISTER_ID: &str = "uxrt2-rqaaa-aaaaq-aaakq-cai";
+
+/// Cycles attached to each call, as required by the exchange rate canister. Unused cycles are
+/// refunded.
+const XRC_CALL_CYCLES: u128 = 1_000_000_000;
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum AssetClass {
+    Cryptocurrency,
+    FiatCurrency,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Asset {
+    pub symbol: String,
+    pub class: AssetClass,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct GetExchangeRateRequest {
+    pub base_asset: Asset,
+    pub quote_asset: Asset,
+    /// Unix timestamp in seconds, the start of the current minute if unset.
+    pub timestamp: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ExchangeRateMetadata {
+    pub decimals: u32,
+    pub base_asset_num_queried_sources: u64,
+    pub base_asset_num_received_rates: u64,
+    pub quote_asset_num_queried_sources: u64,
+    pub quote_asset_num_received_rates: u64,
+    pub standard_deviation: u64,
+    pub forex_timestamp: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ExchangeRate {
+    pub base_asset: Asset,
+    pub quote_asset: Asset,
+    pub timestamp: u64,
+    /// The rate, scaled by `10^metadata.decimals`.
+    pub rate: u64,
+    pub metadata: ExchangeRateMetadata,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OtherError {
+    pub code: u32,
+    pub description: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum ExchangeRateError {
+    AnonymousPrincipalNotAllowed,
+    Pending,
+    CryptoBaseAssetNotFound,
+    CryptoQuoteAssetNotFound,
+    StablecoinRateNotFound,
+    StablecoinRateTooFewRates,
+    StablecoinRateZeroRate,
+    ForexInvalidTimestamp,
+    ForexBaseAssetNotFound,
+    ForexQuoteAssetNotFound,
+    ForexAssetsNotFound,
+    RateLimited,
+    NotEnoughCycles,
+    FailedToAcceptCycles,
+    InconsistentRatesReceived,
+    Other(OtherError),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum GetExchangeRateResult {
+    Ok(ExchangeRate),
+    Err(ExchangeRateError),
+}
+
+impl ExchangeRate {
+    pub fn value(&self) -> f64 {
+        self.rate as f64 / 10f64.powi(self.metadata.decimals as i32)
+    }
+}
+
+/// Returns the number of rupees a US dollar is worth.
+pub async fn usd_inr_rate() -> Result<f64, String> {
+    let fiat = |symbol: &str| Asset {
+        symbol: symbol.to_string(),
+        class: AssetClass::FiatCurrency,
+    };
+    let request = GetExchangeRateRequest {
+        base_asset: fiat("USD"),
+        quote_asset: fiat("INR"),
+        timestamp: None,
+    };
+
+    let xrc = Principal::from_text(XRC_CANISTER_ID).expect("XRC canister id is valid");
+    let (result,): (GetExchangeRateResult,) = ic_cdk::api::call::call_with_payment128(
+        xrc,
+        "get_exchange_rate",
+        (request,),
+        XRC_CALL_CYCLES,
+    )
+    .await
+    .map_err(|(code, message)| {
+        format!("Failed to call the exchange rate canister: {code:?} {message}")
+    })?;
+
+    match result {
+        GetExchangeRateResult::Ok(rate) => Ok(rate.value()),
+        GetExchangeRateResult::Err(e) => Err(format!("Failed to get the USD/INR rate: {e:?}")),
+    }
+}
@@ -1,80 +1,313 @@
-use std::str::FromStr;
+pub mod auth;
+pub mod forex;
+pub mod offramp;
+pub mod onramp;
+pub mod orders;
+pub mod payout_accounts;
+pub mod price_feed;
+pub mod price_oracle;
+pub mod processing;
+pub mod quotes;
 pub mod solana_transactions;
+pub mod state;
+pub mod treasury;
+pub mod webhooks;
+
+use std::str::FromStr;
 
-use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use auth::Role;
+use candid::Principal;
+use ic_cdk::api::management_canister::http_request::{
+    HttpResponse as OutcallResponse, TransformArgs,
+};
+use ic_solana::metrics::{read_metrics, Metrics};
 use ic_solana::types::Pubkey;
-use razorpay::PayoutArgs;
-use solana_transactions::{call_anchor, transfer_sol};
+use orders::{Order, OrderKind};
+use payout_accounts::PayoutAccount;
+use price_oracle::SolPrice;
+use quotes::{Quote, QuoteAmount, QuoteConfig};
+use razorpay::{PayoutArgs, RazorpayClient};
+use solana_transactions::{
+    anchor_initialize_instruction, build_unsigned_transaction, decode_signed_transaction,
+    submit_signed_transaction,
+};
+use webhooks::{HttpRequest, HttpResponse};
+
+#[ic_cdk::init]
+fn init() {
+    processing::schedule();
+    price_feed::schedule();
+}
+
+#[ic_cdk::post_upgrade]
+fn post_upgrade() {
+    processing::schedule();
+    price_feed::schedule();
+    auth::init_metrics();
+}
 
 #[ic_cdk::update]
-pub async fn transfer_sol_from_treasury(to: String, amount: u64) -> String {
-    let keypair_string = option_env!("SOLANA_TREASURY_KEYPAIR").unwrap();
+pub async fn get_treasury_address() -> String {
+    match treasury::treasury_address().await {
+        Ok(address) => address.to_string(),
+        Err(e) => e,
+    }
+}
 
-    let keypair: [u8; 64] = keypair_string
-        .split(',')
-        .map(|s| s.parse::<u8>().unwrap())
-        .collect::<Vec<u8>>()
-        .try_into()
-        .unwrap();
+#[ic_cdk::update]
+pub async fn create_treasury_nonce_account() -> Result<String, String> {
+    auth::require(Role::Operator)?;
+    solana_transactions::create_treasury_nonce_account().await
+}
 
-    let from =
-        Pubkey::from_str(solana_transactions::SOLANA_TREASURY_ADDRESS).expect("Invalid pubkey");
-    transfer_sol(from.to_string(), to, amount, keypair).await
+/// Transfers SOL out of the treasury. On-ramp orders are settled automatically, so this is
+/// restricted to admins.
+#[ic_cdk::update]
+pub async fn transfer_sol_from_treasury(to: String, amount: u64) -> Result<String, String> {
+    auth::require(Role::Admin)?;
+    solana_transactions::transfer_sol_from_treasury(to, amount).await
 }
 
+/// Starts an off-ramp order for the amounts of an unexpired off-ramp quote of the caller, see
+/// [`get_quote`]. The returned order holds the reference key the deposit must include, see
+/// [`offramp`].
 #[ic_cdk::update]
-pub async fn transfer_sol_to_treasury(from: String, amount: u64, keypair: Vec<u8>) -> String {
-    let to =
-        Pubkey::from_str(solana_transactions::SOLANA_TREASURY_ADDRESS).expect("Invalid pubkey");
-    let keypair: [u8; 64] = keypair.as_slice().try_into().unwrap();
-    transfer_sol(from, to.to_string(), amount, keypair).await
+pub async fn create_offramp_order(wallet: String, quote_id: u64) -> Result<Order, String> {
+    auth::require(Role::User)?;
+    offramp::create_order(ic_cdk::caller(), wallet, quote_id).await
 }
 
+/// Builds the unsigned deposit transaction of an off-ramp order, for the wallet to sign.
 #[ic_cdk::update]
-pub async fn transfer_inr(args: PayoutArgs) -> String {
-    let razorpay_public_key = option_env!("RAZORPAY_API_KEY").expect("RAZORPAY_API_KEY not set");
-    let razorpay_secret_key =
-        option_env!("RAZORPAY_SECRET_KEY").expect("RAZORPAY_SECRET_KEY not set");
-    let razorpay_x_acccount =
-        option_env!("RAZORPAY_X_ACCCOUNT").expect("RAZORPAY_X_ACCCOUNT not set");
-    let auth_string = format!("{}:{}", razorpay_public_key, razorpay_secret_key);
+pub async fn build_offramp_deposit(order_id: u64) -> Result<String, String> {
+    auth::require(Role::User)?;
+    offramp::build_deposit_transaction(order_id).await
+}
 
-    let encoded_auth = BASE64.encode(auth_string.as_bytes());
-    let result = razorpay::payout(args, razorpay_x_acccount.to_string(), encoded_auth).await;
-    match result {
-        Ok(res) => res,
-        Err(e) => e,
-    }
+/// Submits the signed deposit transaction of an off-ramp order, returning its signature.
+#[ic_cdk::update]
+pub async fn submit_offramp_deposit(
+    order_id: u64,
+    signed_transaction: String,
+) -> Result<String, String> {
+    auth::require(Role::User)?;
+    offramp::submit_deposit(order_id, signed_transaction).await
 }
 
+/// Verifies the deposit of an off-ramp order, identified by its transaction signature, and pays
+/// out INR to the given account.
 #[ic_cdk::update]
-pub async fn create_order(amount: u64) -> String {
-    let razorpay_public_key = option_env!("RAZORPAY_API_KEY").expect("RAZORPAY_API_KEY not set");
-    let razorpay_secret_key =
-        option_env!("RAZORPAY_SECRET_KEY").expect("RAZORPAY_SECRET_KEY not set");
-    let auth_string = format!("{}:{}", razorpay_public_key, razorpay_secret_key);
+pub async fn confirm_offramp_deposit(
+    order_id: u64,
+    signature: String,
+    payout: PayoutArgs,
+) -> Result<Order, String> {
+    auth::require(Role::User)?;
+    offramp::confirm_deposit(order_id, signature, payout).await
+}
 
-    let encoded_auth = BASE64.encode(auth_string.as_bytes());
-    let result = razorpay::create_order(amount, encoded_auth).await;
-    match result {
-        Ok(res) => res,
-        Err(e) => e,
+/// Starts an on-ramp order for the amounts of an unexpired on-ramp quote of the caller, see
+/// [`get_quote`], paid through Razorpay. Once the payment is captured, the SOL is sent to
+/// `wallet`, see [`onramp`]. If the Razorpay order can't be created right away, the order is
+/// returned without it, and it is created when the order is next processed.
+#[ic_cdk::update]
+pub async fn create_order(quote_id: u64, wallet: String) -> Result<Order, String> {
+    auth::require(Role::User)?;
+    Pubkey::from_str(&wallet).map_err(|e| e.to_string())?;
+
+    let client = razorpay_client()?;
+
+    let quote = quotes::take(quote_id, ic_cdk::caller(), OrderKind::OnRamp)?;
+    let order = orders::create(ic_cdk::caller(), wallet, &quote);
+    let receipt = onramp::order_receipt(order.id);
+    match client.create_order(order.inr_amount, &receipt).await {
+        Ok(razorpay_order) => orders::update(order.id, |order| {
+            order.razorpay_order_id = Some(razorpay_order.id)
+        }),
+        // The Razorpay order is looked up by its receipt, or created, when the order is next
+        // processed.
+        Err(e) => {
+            ic_cdk::api::print(format!(
+                "Razorpay order of order {} not created: {e}",
+                order.id
+            ));
+            Ok(order)
+        }
     }
 }
 
+/// Confirms the payment of an on-ramp order with the `razorpay_payment_id` and
+/// `razorpay_signature` returned by Razorpay Checkout.
 #[ic_cdk::update]
-pub async fn get_sol_price() -> String {
-    let timestamp = ic_cdk::api::time().checked_div(1000_000_000u64).unwrap();
-    let price = solana_transactions::get_sol_price(timestamp).await;
-    match price {
-        Ok(price) => price.to_string(),
-        Err(e) => e,
-    }
+pub async fn confirm_order_payment(
+    order_id: u64,
+    razorpay_payment_id: String,
+    razorpay_signature: String,
+) -> Result<Order, String> {
+    auth::require(Role::User)?;
+    onramp::confirm_payment(order_id, razorpay_payment_id, razorpay_signature).await
 }
 
+/// Returns an order of the caller. Operators can read any order.
+#[ic_cdk::query]
+pub fn get_order(order_id: u64) -> Result<Option<Order>, String> {
+    let Some(order) = orders::get(order_id) else {
+        return Ok(None);
+    };
+    auth::require_self_or(&order.owner, Role::Operator)?;
+    Ok(Some(order))
+}
+
+/// Returns the Razorpay contact and fund accounts of the caller, reused across payouts.
+#[ic_cdk::query]
+pub fn get_payout_account() -> Option<PayoutAccount> {
+    payout_accounts::get(&ic_cdk::caller())
+}
+
+/// Lists the orders of a user, oldest first. Users can only list their own orders, and
+/// operators those of anyone.
+#[ic_cdk::query]
+pub fn list_orders(user: Principal) -> Result<Vec<Order>, String> {
+    auth::require_self_or(&user, Role::Operator)?;
+    Ok(orders::list_by_owner(&user))
+}
+
+/// Returns the price of SOL in USD over the last closed minute, aggregated from several
+/// exchanges, see [`price_oracle`]. The cached price is returned unless it is stale, see
+/// [`price_feed`].
 #[ic_cdk::update]
-pub async fn call_anchor_program(from: String, keypair: Vec<u8>) -> String {
-    call_anchor(from, keypair.try_into().expect("Invalid keypair")).await
+pub async fn get_sol_price() -> Result<SolPrice, String> {
+    auth::require(Role::User)?;
+    price_feed::sol_price().await
+}
+
+/// Returns the latest cached price of SOL in USD, which may be stale.
+#[ic_cdk::query]
+pub fn get_latest_sol_price() -> Option<SolPrice> {
+    price_feed::latest()
+}
+
+/// Returns the cached prices of SOL in USD, one per minute, oldest first.
+#[ic_cdk::query]
+pub fn get_sol_price_history() -> Vec<SolPrice> {
+    price_feed::history()
+}
+
+/// Quotes an order for `amount` at the current SOL/INR rate, spread and fee included, see
+/// [`quotes`]. The quote can be ordered by the caller until it expires.
+#[ic_cdk::update]
+pub async fn get_quote(kind: OrderKind, amount: QuoteAmount) -> Result<Quote, String> {
+    auth::require(Role::User)?;
+    quotes::get_quote(ic_cdk::caller(), kind, amount).await
+}
+
+#[ic_cdk::query]
+pub fn get_quote_config() -> QuoteConfig {
+    quotes::get_config()
+}
+
+#[ic_cdk::update]
+pub fn set_quote_config(config: QuoteConfig) -> Result<(), String> {
+    auth::require(Role::Admin)?;
+    quotes::set_config(config)
+}
+
+#[ic_cdk::query(hidden = true)]
+fn transform_price_response(args: TransformArgs) -> OutcallResponse {
+    price_oracle::transform_price_response(args)
+}
+
+#[ic_cdk::query(hidden = true)]
+fn transform_razorpay_response(args: TransformArgs) -> OutcallResponse {
+    razorpay::transform_response(args)
+}
+
+/// Builds an unsigned transaction initializing the Anchor program for `from`, for the wallet to
+/// sign. Returns the base64 encoded transaction.
+#[ic_cdk::update]
+pub async fn build_anchor_call(from: String) -> Result<String, String> {
+    auth::require(Role::User)?;
+    let from = Pubkey::from_str(&from).map_err(|e| e.to_string())?;
+    let instruction = anchor_initialize_instruction(&from);
+    build_unsigned_transaction(&[instruction], &from).await
+}
+
+/// Submits a transaction built by [`build_anchor_call`] once signed by the wallet, returning its
+/// signature.
+#[ic_cdk::update]
+pub async fn submit_anchor_call(signed_transaction: String) -> Result<String, String> {
+    auth::require(Role::User)?;
+    let tx = decode_signed_transaction(&signed_transaction)?;
+    let from = *tx
+        .message
+        .account_keys
+        .first()
+        .ok_or("Transaction has no fee payer")?;
+    let instruction = anchor_initialize_instruction(&from);
+    submit_signed_transaction(tx, &[instruction], &from).await
+}
+
+/// Grants a role to a principal, replacing its previous role. Restricted to controllers, see
+/// [`auth`].
+#[ic_cdk::update]
+pub fn grant_role(principal: Principal, role: Role) -> Result<(), String> {
+    auth::grant(principal, role)
+}
+
+/// Revokes the role of a principal. Restricted to controllers.
+#[ic_cdk::update]
+pub fn revoke_role(principal: Principal) -> Result<(), String> {
+    auth::revoke(principal)
+}
+
+/// Returns the role of a principal. Controllers are admins.
+#[ic_cdk::query]
+pub fn get_role(principal: Principal) -> Option<Role> {
+    auth::role(&principal)
+}
+
+/// Lists the granted roles.
+#[ic_cdk::query]
+pub fn list_roles() -> Result<Vec<(Principal, Role)>, String> {
+    auth::require(Role::Operator)?;
+    Ok(auth::list())
+}
+
+#[ic_cdk::query]
+pub fn get_metrics() -> Metrics {
+    read_metrics(Metrics::clone)
+}
+
+/// Serves the HTTP interface of the canister, which receives Razorpay webhooks, see [`webhooks`].
+#[ic_cdk::query]
+pub fn http_request(request: HttpRequest) -> HttpResponse {
+    webhooks::http_request(request)
+}
+
+#[ic_cdk::update]
+pub fn http_request_update(request: HttpRequest) -> HttpResponse {
+    webhooks::http_request_update(request)
+}
+
+/// Returns a setting configured at build time through the environment variable `name`, or an
+/// error if the canister was built without it.
+pub(crate) fn build_setting(
+    name: &str,
+    value: Option<&'static str>,
+) -> Result<&'static str, String> {
+    value.ok_or_else(|| format!("{name} was not set at build time"))
+}
+
+/// Client of the Razorpay API, with the credentials configured at build time.
+pub(crate) fn razorpay_client() -> Result<RazorpayClient, String> {
+    let key_id = build_setting("RAZORPAY_API_KEY", option_env!("RAZORPAY_API_KEY"))?;
+    let key_secret = build_setting("RAZORPAY_SECRET_KEY", option_env!("RAZORPAY_SECRET_KEY"))?;
+    Ok(RazorpayClient::new(key_id, key_secret).with_transform("transform_razorpay_response"))
+}
+
+/// The RazorpayX account payouts are made from, configured at build time.
+pub(crate) fn razorpay_x_account() -> Result<&'static str, String> {
+    build_setting("RAZORPAY_X_ACCCOUNT", option_env!("RAZORPAY_X_ACCCOUNT"))
 }
 
 ic_cdk::export_candid!();
@@ -0,0 +1,480 @@
+//! SOL-to-INR off-ramp.
+//!
+//! An off-ramp order is created from a quote of the SOL amount the user sells and the INR amount
+//! paid out for it, see [`crate::quotes`], along with a reference key. The user signs a system
+//! transfer of that amount to the treasury, with the reference key appended as an extra
+//! read-only account, as in Solana Pay. The canister builds the unsigned transaction and submits
+//! it once signed, so private keys never leave the wallet.
+//! The canister then fetches the finalized deposit, checks it against the order and only then
+//! pays out INR, unless the rate fell by more than the allowed slippage since the quote. The
+//! reference key is unique to the order, so a deposit can't pay out twice.
+//! The order settles when Razorpay's `payout.processed` webhook arrives, see [`crate::webhooks`],
+//! or when the [`crate::processing`] timer finds the payout processed. A payout that fails or is
+//! reversed, or refused for slippage, has the deposit returned to the wallet instead, so the user
+//! doesn't lose their SOL.
+
+use std::str::FromStr;
+
+use ic_solana::{
+    system_instruction::{self, SystemInstruction},
+    types::{AccountMeta, Instruction, Pubkey, Signature, Transaction, VersionedTransaction},
+};
+use razorpay::{Payout, PayoutArgs, PayoutFilter, PayoutMode, RazorpayError};
+
+use crate::{
+    onramp::MAX_SEND_ATTEMPTS,
+    orders::{self, Order, OrderKind, OrderState},
+    payout_accounts, quotes, razorpay_client, razorpay_x_account,
+    solana_transactions::{
+        build_unsigned_transaction, decode_signed_transaction, get_finalized_transaction,
+        rebroadcast, sign_transfer_from_treasury, submit_signed_transaction,
+        treasury_transfer_status, TreasuryTransferStatus,
+    },
+    treasury::treasury_address,
+};
+
+/// How long a payout may stay requested without an id before it is looked up, in nanoseconds.
+pub const PAYOUT_TIMEOUT: u64 = 10 * 60 * 1_000_000_000;
+
+/// Returns the reference key that the deposit of an off-ramp order must include.
+pub fn reference_key(treasury: &Pubkey, order_id: u64) -> Pubkey {
+    Pubkey::create_with_seed(
+        treasury,
+        &format!("offramp-{order_id}"),
+        &system_instruction::ID,
+    )
+    .expect("reference seed is valid")
+}
+
+/// Whether the transaction transfers `lamports` from `from` to `to`, referencing `reference`.
+pub fn is_deposit(
+    tx: &VersionedTransaction,
+    from: &Pubkey,
+    to: &Pubkey,
+    lamports: u64,
+    reference: &Pubkey,
+) -> bool {
+    let keys = tx.message.static_account_keys();
+    let key = |index: &u8| keys.get(*index as usize);
+
+    tx.message.instructions().iter().any(|instruction| {
+        key(&instruction.program_id_index) == Some(&system_instruction::ID)
+            && bincode::deserialize::<SystemInstruction>(&instruction.data).ok()
+                == Some(SystemInstruction::Transfer { lamports })
+            && instruction.accounts.first().and_then(key) == Some(from)
+            && instruction.accounts.get(1).and_then(key) == Some(to)
+            && instruction.accounts[2..]
+                .iter()
+                .any(|index| key(index) == Some(reference))
+    })
+}
+
+/// Returns the deposit instruction of an off-ramp order: a transfer from the wallet of the user to
+/// the treasury, referencing the order.
+pub fn deposit_instruction(order: &Order, treasury: &Pubkey) -> Result<Instruction, String> {
+    let wallet = Pubkey::from_str(&order.wallet).map_err(|e| e.to_string())?;
+    let mut instruction = system_instruction::transfer(&wallet, treasury, order.lamports);
+    instruction.accounts.push(AccountMeta::new_readonly(
+        reference_key(treasury, order.id),
+        false,
+    ));
+    Ok(instruction)
+}
+
+/// Records a new off-ramp order for the amounts of a quote, returning it along with its
+/// reference key.
+pub async fn create_order(
+    owner: candid::Principal,
+    wallet: String,
+    quote_id: u64,
+) -> Result<Order, String> {
+    Pubkey::from_str(&wallet).map_err(|e| e.to_string())?;
+    let treasury = treasury_address().await?;
+
+    let quote = quotes::take(quote_id, owner, OrderKind::OffRamp)?;
+    let order = orders::create(owner, wallet, &quote);
+    orders::update(order.id, |order| {
+        order.reference = Some(reference_key(&treasury, order.id).to_string())
+    })
+}
+
+/// Builds the unsigned deposit transaction of an off-ramp order, for the wallet of the user to
+/// sign. Returns the base64 encoded transaction.
+pub async fn build_deposit_transaction(order_id: u64) -> Result<String, String> {
+    let order = owned_order(order_id)?;
+    if order.state != OrderState::Created {
+        return Err(format!("Order {order_id} is {:?}", order.state));
+    }
+    let treasury = treasury_address().await?;
+    let wallet = Pubkey::from_str(&order.wallet).map_err(|e| e.to_string())?;
+
+    let instruction = deposit_instruction(&order, &treasury)?;
+    build_unsigned_transaction(&[instruction], &wallet).await
+}
+
+/// Submits the deposit transaction of an off-ramp order once signed by the wallet, returning
+/// its signature. The transaction must match the one built by [`build_deposit_transaction`].
+pub async fn submit_deposit(order_id: u64, signed_transaction: String) -> Result<String, String> {
+    let order = owned_order(order_id)?;
+    if order.state != OrderState::Created {
+        return Err(format!("Order {order_id} is {:?}", order.state));
+    }
+    let treasury = treasury_address().await?;
+    let wallet = Pubkey::from_str(&order.wallet).map_err(|e| e.to_string())?;
+
+    let tx = decode_signed_transaction(&signed_transaction)?;
+    let instruction = deposit_instruction(&order, &treasury)?;
+    let signature = submit_signed_transaction(tx, &[instruction], &wallet).await?;
+
+    orders::update(order_id, |order| {
+        order.solana_signature = Some(signature.clone())
+    })?;
+    Ok(signature)
+}
+
+/// Verifies the deposit of an off-ramp order, then pays out the quoted INR amount.
+///
+/// The order stays `Sending` until the payout is processed. If the payout can't be requested,
+/// the order stays paid and calling this again retries the payout. If the rate slipped too far
+/// since the quote, or Razorpay fails or reverses the payout later, the deposit is refunded.
+pub async fn confirm_deposit(
+    order_id: u64,
+    signature: String,
+    payout: PayoutArgs,
+) -> Result<Order, String> {
+    let order = owned_order(order_id)?;
+    payout.validate(order.inr_amount)?;
+    match order.state {
+        OrderState::Created => {
+            verify_deposit(&order, &signature).await?;
+
+            // Checked again: another call may have confirmed the order while the deposit was
+            // fetched.
+            let order = offramp_order(order_id)?;
+            if order.state != OrderState::Created {
+                return Err(format!("Order {order_id} is {:?}", order.state));
+            }
+            orders::update(order_id, |order| {
+                order.solana_signature = Some(signature);
+                order.state = OrderState::Paid;
+            })?;
+        }
+        OrderState::Paid => {}
+        state => return Err(format!("Order {order_id} is {state:?}")),
+    }
+
+    pay_out(order_id, payout).await
+}
+
+async fn verify_deposit(order: &Order, signature: &str) -> Result<(), String> {
+    let signature = Signature::from_str(signature).map_err(|e| e.to_string())?;
+    let treasury = treasury_address().await?;
+    let wallet = Pubkey::from_str(&order.wallet).map_err(|e| e.to_string())?;
+    let reference = reference_key(&treasury, order.id);
+
+    let tx = get_finalized_transaction(&signature).await?;
+    if tx.signatures.first() != Some(&signature) {
+        return Err(format!(
+            "Transaction {signature} does not match its signature"
+        ));
+    }
+    if !is_deposit(&tx, &wallet, &treasury, order.lamports, &reference) {
+        return Err(format!(
+            "Transaction {signature} is not a deposit of {} lamports from {wallet} to the \
+             treasury referencing {reference}",
+            order.lamports
+        ));
+    }
+    Ok(())
+}
+
+async fn pay_out(order_id: u64, payout: PayoutArgs) -> Result<Order, String> {
+    let razorpay_x_acccount = razorpay_x_account()?;
+
+    let order = offramp_order(order_id)?;
+    if quotes::slippage_exceeded(&order).await? {
+        refuse_payout(order_id)?;
+        return Err(format!(
+            "The rate moved beyond the allowed slippage, refunding order {order_id}"
+        ));
+    }
+
+    // Checked again: another call may have paid out the order while the rate was fetched.
+    let order = offramp_order(order_id)?;
+    if order.state != OrderState::Paid {
+        return Err(format!("Order {order_id} is {:?}", order.state));
+    }
+    // Guards against paying out twice while the payout is in flight.
+    let order = orders::update(order_id, |order| order.state = OrderState::Sending)?;
+
+    let fund_account_id = match payout_accounts::fund_account_id(&order.owner, &payout).await {
+        Ok(fund_account_id) => fund_account_id,
+        Err(e) => {
+            orders::update(order_id, |order| order.state = OrderState::Paid)?;
+            return Err(format!("Failed to pay out order {order_id}: {e}"));
+        }
+    };
+
+    let idempotency_key = payout_idempotency_key(order_id, &fund_account_id, payout.mode);
+    let result = razorpay_client()?
+        .payout(
+            fund_account_id,
+            order.inr_amount,
+            payout.mode,
+            payout_reference(order_id),
+            razorpay_x_acccount.to_string(),
+            &idempotency_key,
+        )
+        .await;
+    match result {
+        // Settled once the payout is processed, see `crate::webhooks` and `reconcile_payout`.
+        Ok(payout) => orders::update(order_id, |order| order.razorpay_payout_id = Some(payout.id)),
+        // Razorpay rejected a request, so no payout was made.
+        Err(
+            e @ RazorpayError::Api {
+                status: 400..=499, ..
+            },
+        ) => {
+            orders::update(order_id, |order| order.state = OrderState::Paid)?;
+            Err(format!("Failed to pay out order {order_id}: {e}"))
+        }
+        // The payout may have been made; it is looked up by reference once `PAYOUT_TIMEOUT`
+        // elapses.
+        Err(e) => Err(format!("Payout of order {order_id} is pending: {e}")),
+    }
+}
+
+/// Refunds the deposit of a paid order instead of paying it out.
+fn refuse_payout(order_id: u64) -> Result<(), String> {
+    let order = offramp_order(order_id)?;
+    if order.state == OrderState::Paid {
+        orders::update(order_id, |order| order.state = OrderState::Refunding)?;
+    }
+    Ok(())
+}
+
+/// Applies the status of a payout, reported by Razorpay or polled, to its off-ramp order. A
+/// failed or reversed payout starts refunding the deposit, even after the order settled.
+pub fn record_payout_status(order_id: u64, payout: &Payout) -> Result<(), String> {
+    let order = offramp_order(order_id)?;
+    if payout.is_processed() && order.state == OrderState::Sending {
+        orders::update(order_id, |order| order.state = OrderState::Settled)?;
+    } else if payout.is_failed() && matches!(order.state, OrderState::Sending | OrderState::Settled)
+    {
+        ic_cdk::api::print(format!(
+            "Payout {} of order {order_id} {}: {}",
+            payout.id,
+            payout.status,
+            payout
+                .failure_reason
+                .as_deref()
+                .unwrap_or("no reason given")
+        ));
+        orders::update(order_id, |order| {
+            order.signed_transaction = None;
+            order.state = OrderState::Refunding;
+        })?;
+    }
+    Ok(())
+}
+
+/// Advances an off-ramp order by at most one step. The user drives the order until the payout
+/// is requested; from then on, payouts that Razorpay didn't report are polled and failed payouts
+/// are refunded.
+pub async fn process_order(order: Order) -> Result<(), String> {
+    match order.state {
+        OrderState::Sending => reconcile_payout(order).await,
+        OrderState::Refunding => refund(order).await,
+        _ => Ok(()),
+    }
+}
+
+async fn reconcile_payout(order: Order) -> Result<(), String> {
+    if let Some(payout_id) = order.razorpay_payout_id.clone() {
+        let payout = razorpay_client()?.fetch_payout(&payout_id).await?;
+        return record_payout_status(order.id, &payout);
+    }
+
+    // The payout request may still be in flight, or its response was lost.
+    if ic_cdk::api::time().saturating_sub(order.updated_at) < PAYOUT_TIMEOUT {
+        return Ok(());
+    }
+    let razorpay_x_acccount = razorpay_x_account()?;
+    let filter = PayoutFilter {
+        reference_id: Some(payout_reference(order.id)),
+        ..Default::default()
+    };
+    let payouts = razorpay_client()?
+        .list_payouts(razorpay_x_acccount, &filter)
+        .await?;
+
+    match payouts.first() {
+        Some(payout) => {
+            orders::update(order.id, |order| {
+                order.razorpay_payout_id = Some(payout.id.clone())
+            })?;
+            record_payout_status(order.id, payout)
+        }
+        // No payout was found, so the user can retry it. Should the listing have missed it,
+        // a retry to the same destination returns it, see `payout_idempotency_key`.
+        None => {
+            orders::update(order.id, |order| order.state = OrderState::Paid)?;
+            Ok(())
+        }
+    }
+}
+
+/// Returns the deposit of an order to its wallet, with the same exactly-once guarantee as
+/// on-ramp transfers, see [`crate::onramp`]. After [`MAX_SEND_ATTEMPTS`] transfers that didn't
+/// land, the order fails and is left to operators.
+async fn refund(order: Order) -> Result<(), String> {
+    if let Some(tx) = order.signed_transaction.as_deref() {
+        let tx = Transaction::from_str(tx).map_err(|_| "Invalid signed transaction")?;
+        match treasury_transfer_status(&tx).await? {
+            TreasuryTransferStatus::Confirmed => {
+                orders::update(order.id, |order| order.state = OrderState::Refunded)?;
+                return Ok(());
+            }
+            TreasuryTransferStatus::Pending => {
+                rebroadcast(tx).await?;
+                return Ok(());
+            }
+            TreasuryTransferStatus::Failed(err) => {
+                ic_cdk::api::print(format!("Refund of order {} failed: {err}", order.id));
+            }
+            TreasuryTransferStatus::Dropped => {}
+        }
+    }
+
+    if order.send_attempts >= MAX_SEND_ATTEMPTS {
+        ic_cdk::api::print(format!(
+            "Refund of order {} not sent after {} attempts, {} lamports are owed to {}",
+            order.id, order.send_attempts, order.lamports, order.wallet
+        ));
+        orders::update(order.id, |order| {
+            order.state = OrderState::Failed("Refund not sent".to_string())
+        })?;
+        return Ok(());
+    }
+
+    let tx = sign_transfer_from_treasury(&order.wallet, order.lamports).await?;
+
+    // Recorded before broadcasting, so a crash can't lose track of a refund that may land.
+    orders::update(order.id, |order| {
+        order.signed_transaction = Some(tx.to_string());
+        order.send_attempts += 1;
+    })?;
+
+    // Whether the refund landed is checked on the next run.
+    rebroadcast(tx).await?;
+    Ok(())
+}
+
+/// Reference of the payout of an order, to find it again if its id was lost.
+fn payout_reference(order_id: u64) -> String {
+    format!("offramp-{order_id}")
+}
+
+/// Key of a payout request, so retrying it returns the payout it made instead of paying out
+/// again. It covers the destination and mode too: Razorpay rejects a key reused with different
+/// details, which a user retrying after a rejected payout may send.
+fn payout_idempotency_key(order_id: u64, fund_account_id: &str, mode: PayoutMode) -> String {
+    format!(
+        "{}-{fund_account_id}-{}",
+        payout_reference(order_id),
+        mode.as_str()
+    )
+}
+
+fn offramp_order(order_id: u64) -> Result<Order, String> {
+    let order = orders::get(order_id).ok_or_else(|| format!("Order {order_id} not found"))?;
+    if order.kind != OrderKind::OffRamp {
+        return Err(format!("Order {order_id} is not an off-ramp order"));
+    }
+    Ok(order)
+}
+
+/// Returns an off-ramp order of the caller.
+fn owned_order(order_id: u64) -> Result<Order, String> {
+    let order = offramp_order(order_id)?;
+    if order.owner != ic_cdk::caller() {
+        return Err(format!("Order {order_id} belongs to another user"));
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_solana::types::{BlockHash, Message};
+
+    use super::*;
+
+    const LAMPORTS: u64 = 1_000_000;
+
+    fn deposit(instruction: Instruction, payer: &Pubkey) -> VersionedTransaction {
+        let message =
+            Message::new_with_blockhash(&[instruction], Some(payer), &BlockHash::default());
+        Transaction::new_unsigned(message).into()
+    }
+
+    fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64, reference: &Pubkey) -> Instruction {
+        let mut instruction = system_instruction::transfer(from, to, lamports);
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(*reference, false));
+        instruction
+    }
+
+    #[test]
+    fn test_reference_key() {
+        let treasury = Pubkey::from([1; 32]);
+        assert_eq!(reference_key(&treasury, 1), reference_key(&treasury, 1));
+        assert_ne!(reference_key(&treasury, 1), reference_key(&treasury, 2));
+        assert_ne!(
+            reference_key(&treasury, 1),
+            reference_key(&Pubkey::from([2; 32]), 1)
+        );
+    }
+
+    #[test]
+    fn test_is_deposit() {
+        let wallet = Pubkey::from([1; 32]);
+        let treasury = Pubkey::from([2; 32]);
+        let reference = reference_key(&treasury, 1);
+        let accepts =
+            |tx: &VersionedTransaction| is_deposit(tx, &wallet, &treasury, LAMPORTS, &reference);
+
+        let tx = deposit(transfer(&wallet, &treasury, LAMPORTS, &reference), &wallet);
+        assert!(accepts(&tx));
+
+        let tx = deposit(
+            transfer(&wallet, &treasury, LAMPORTS - 1, &reference),
+            &wallet,
+        );
+        assert!(!accepts(&tx), "wrong amount");
+
+        let other = Pubkey::from([3; 32]);
+        let tx = deposit(transfer(&wallet, &other, LAMPORTS, &reference), &wallet);
+        assert!(!accepts(&tx), "wrong destination");
+
+        let tx = deposit(transfer(&other, &treasury, LAMPORTS, &reference), &other);
+        assert!(!accepts(&tx), "wrong sender");
+
+        let tx = deposit(
+            transfer(&wallet, &treasury, LAMPORTS, &reference_key(&treasury, 2)),
+            &wallet,
+        );
+        assert!(!accepts(&tx), "reference of another order");
+
+        let tx = deposit(
+            system_instruction::transfer(&wallet, &treasury, LAMPORTS),
+            &wallet,
+        );
+        assert!(!accepts(&tx), "missing reference");
+
+        let mut instruction = transfer(&wallet, &treasury, LAMPORTS, &reference);
+        instruction.program_id = other;
+        let tx = deposit(instruction, &wallet);
+        assert!(!accepts(&tx), "not the system program");
+    }
+}
@@ -0,0 +1,233 @@
+//! Fiat-to-SOL on-ramp.
+//!
+//! An on-ramp order is created from a quote, see [`crate::quotes`], with the Razorpay order the
+//! user pays and the SOL amount quoted for it. The [`processing`] timer then drives each active
+//! order through its states:
+//!
+//! - `Created`: creates the Razorpay order if that failed along with the order, looking it up by
+//!   its receipt first. Then waits for a captured payment of the order amount, or fails once
+//!   [`PAYMENT_TIMEOUT`] elapses. Razorpay's `payment.captured` webhook, see [`crate::webhooks`],
+//!   and the checkout callback, see [`confirm_payment`], mark the order paid right away; polling
+//!   catches payments both missed.
+//! - `Paid`: signs a treasury transfer with the treasury's durable nonce and records it before
+//!   broadcasting, moving to `Sending`. After [`MAX_SEND_ATTEMPTS`], or if the rate rose by more
+//!   than the allowed slippage since the quote, the payment is refunded.
+//! - `Sending`: settles the order once the transfer is confirmed. A transfer that failed, or was
+//!   dropped because the nonce advanced without it, can't land anymore and moves the order back
+//!   to `Paid`. Transactions signed with the same nonce exclude each other, so SOL is released
+//!   exactly once.
+
+use std::str::FromStr;
+
+use ic_solana::types::Transaction;
+use razorpay::Payment;
+
+use crate::{
+    orders::{self, Order, OrderKind, OrderState},
+    processing, quotes, razorpay_client,
+    solana_transactions::{
+        rebroadcast, sign_transfer_from_treasury, treasury_transfer_status, TreasuryTransferStatus,
+    },
+};
+
+/// How long the user has to pay an order, in nanoseconds.
+pub const PAYMENT_TIMEOUT: u64 = 30 * 60 * 1_000_000_000;
+
+/// Number of treasury transfers signed for an order before refunding it. Off-ramp refunds are
+/// capped by it too, see [`crate::offramp`].
+pub const MAX_SEND_ATTEMPTS: u32 = 3;
+
+/// Advances an on-ramp order by at most one step.
+pub async fn process_order(order: Order) -> Result<(), String> {
+    match order.state {
+        OrderState::Created => check_payment(order).await,
+        OrderState::Paid if order.send_attempts >= MAX_SEND_ATTEMPTS => refund(order).await,
+        OrderState::Paid => send(order).await,
+        OrderState::Sending => check_transfer(order).await,
+        OrderState::Refunding
+        | OrderState::Settled
+        | OrderState::Failed(_)
+        | OrderState::Refunded => Ok(()),
+    }
+}
+
+async fn check_payment(order: Order) -> Result<(), String> {
+    let razorpay_order_id = razorpay_order_id(&order).await?;
+    let payments = razorpay_client()?
+        .fetch_order_payments(&razorpay_order_id)
+        .await?;
+
+    let Some(payment) = payments.into_iter().find(|payment| payment.is_captured()) else {
+        if ic_cdk::api::time().saturating_sub(order.created_at) > PAYMENT_TIMEOUT {
+            orders::update(order.id, |order| {
+                order.state = OrderState::Failed("Payment not received in time".to_string())
+            })?;
+        }
+        return Ok(());
+    };
+
+    if !pays_for(&order, &payment) {
+        ic_cdk::api::print(format!(
+            "Order {} was paid {} {} instead of {} INR",
+            order.id, payment.amount, payment.currency, order.inr_amount
+        ));
+        // The order stays created until the refund goes through, so it is retried.
+        razorpay_client()?
+            .refund_payment(&payment.id, payment.amount, &refund_reference(order.id))
+            .await?;
+        orders::update(order.id, |order| {
+            order.razorpay_payment_id = Some(payment.id);
+            order.state = OrderState::Refunded;
+        })?;
+        return Ok(());
+    }
+
+    orders::update(order.id, |order| {
+        order.razorpay_payment_id = Some(payment.id);
+        order.state = OrderState::Paid;
+    })?;
+    Ok(())
+}
+
+/// Returns the Razorpay order of an on-ramp order. If creating it failed when the order was
+/// created, the Razorpay order is looked up by its receipt, in case Razorpay did create it, and
+/// only created again if not found.
+async fn razorpay_order_id(order: &Order) -> Result<String, String> {
+    if let Some(razorpay_order_id) = &order.razorpay_order_id {
+        return Ok(razorpay_order_id.clone());
+    }
+    let client = razorpay_client()?;
+    let receipt = order_receipt(order.id);
+    let razorpay_order = match client.find_order_by_receipt(&receipt).await? {
+        Some(razorpay_order) => razorpay_order,
+        None => client.create_order(order.inr_amount, &receipt).await?,
+    };
+    orders::update(order.id, |order| {
+        order.razorpay_order_id = Some(razorpay_order.id.clone())
+    })?;
+    Ok(razorpay_order.id)
+}
+
+/// Confirms the payment of an on-ramp order with the details Razorpay Checkout returns to the
+/// user, then starts sending its SOL.
+///
+/// The signature proves the payment was made against the Razorpay order of this order, and the
+/// payment is fetched from Razorpay to check it was captured in full, so the order isn't marked
+/// paid on the client's word. A payment that isn't captured yet is left to [`processing`].
+pub async fn confirm_payment(
+    order_id: u64,
+    payment_id: String,
+    signature: String,
+) -> Result<Order, String> {
+    let client = razorpay_client()?;
+
+    let order = orders::get(order_id).ok_or_else(|| format!("Order {order_id} not found"))?;
+    if order.kind != OrderKind::OnRamp {
+        return Err(format!("Order {order_id} is not an on-ramp order"));
+    }
+    if order.owner != ic_cdk::caller() {
+        return Err(format!("Order {order_id} belongs to another user"));
+    }
+    let razorpay_order_id = order
+        .razorpay_order_id
+        .clone()
+        .ok_or("Order has no Razorpay order")?;
+    if !client.verify_payment_signature(&razorpay_order_id, &payment_id, &signature) {
+        return Err("Invalid payment signature".to_string());
+    }
+    if order.state != OrderState::Created {
+        return Ok(order);
+    }
+
+    let payments = client.fetch_order_payments(&razorpay_order_id).await?;
+    let payment = payments
+        .into_iter()
+        .find(|payment| payment.id == payment_id)
+        .ok_or_else(|| format!("Payment {payment_id} not found"))?;
+
+    // Checked again: the order may have progressed while the payment was fetched. Payments of
+    // the wrong amount are refunded when the order is next processed.
+    let order = orders::get(order_id).ok_or_else(|| format!("Order {order_id} not found"))?;
+    if order.state != OrderState::Created || !payment.is_captured() || !pays_for(&order, &payment) {
+        return Ok(order);
+    }
+    let order = orders::update(order_id, |order| {
+        order.razorpay_payment_id = Some(payment.id);
+        order.state = OrderState::Paid;
+    })?;
+    ic_cdk::spawn(processing::process_orders());
+    Ok(order)
+}
+
+/// Whether a captured payment pays the full amount of an on-ramp order.
+pub fn pays_for(order: &Order, payment: &Payment) -> bool {
+    payment.amount == order.inr_amount && payment.currency == "INR"
+}
+
+async fn send(order: Order) -> Result<(), String> {
+    if quotes::slippage_exceeded(&order).await? {
+        return refund(order).await;
+    }
+    let tx = sign_transfer_from_treasury(&order.wallet, order.lamports).await?;
+
+    // Recorded before broadcasting, so a crash can't lose track of a transfer that may land.
+    orders::update(order.id, |order| {
+        order.solana_signature = Some(tx.signatures[0].to_string());
+        order.signed_transaction = Some(tx.to_string());
+        order.send_attempts += 1;
+        order.state = OrderState::Sending;
+    })?;
+
+    // Not awaiting confirmation keeps the order from blocking the others; whether the transfer
+    // landed is checked on the next run.
+    rebroadcast(tx).await?;
+    Ok(())
+}
+
+async fn check_transfer(order: Order) -> Result<(), String> {
+    let tx = order
+        .signed_transaction
+        .as_deref()
+        .ok_or("Order has no signed transaction")
+        .and_then(|tx| Transaction::from_str(tx).map_err(|_| "Invalid signed transaction"))?;
+
+    match treasury_transfer_status(&tx).await? {
+        TreasuryTransferStatus::Confirmed => {
+            orders::update(order.id, |order| order.state = OrderState::Settled)?;
+        }
+        TreasuryTransferStatus::Failed(err) => {
+            ic_cdk::api::print(format!("Transfer of order {} failed: {err}", order.id));
+            orders::update(order.id, |order| order.state = OrderState::Paid)?;
+        }
+        TreasuryTransferStatus::Dropped => {
+            orders::update(order.id, |order| order.state = OrderState::Paid)?;
+        }
+        TreasuryTransferStatus::Pending => {
+            rebroadcast(tx).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn refund(order: Order) -> Result<(), String> {
+    let payment_id = order
+        .razorpay_payment_id
+        .clone()
+        .ok_or("Order has no captured payment")?;
+    razorpay_client()?
+        .refund_payment(&payment_id, order.inr_amount, &refund_reference(order.id))
+        .await?;
+    orders::update(order.id, |order| order.state = OrderState::Refunded)?;
+    Ok(())
+}
+
+/// Receipt of the Razorpay order of an on-ramp order, which also keys its creation so it is
+/// created at most once.
+pub fn order_receipt(order_id: u64) -> String {
+    format!("onramp-order-{order_id}")
+}
+
+/// Key of the refund of an order, so it is refunded at most once.
+fn refund_reference(order_id: u64) -> String {
+    format!("onramp-refund-{order_id}")
+}
@@ -0,0 +1,224 @@
+//! Ledger of on-ramp and off-ramp orders.
+//!
+//! Every flow moving INR or SOL is recorded as an [`Order`] before any funds move, and updated
+//! as each step completes, so the canister can tell who was paid what after an upgrade or a
+//! failed call.
+
+use std::borrow::Cow;
+
+use candid::{CandidType, Decode, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::Deserialize;
+
+use crate::{
+    quotes::Quote,
+    state::{
+        principal_key, ACTIVE_ORDERS, NEXT_ORDER_ID, ORDERS, ORDERS_BY_OWNER, ORDERS_BY_RAZORPAY_ID,
+    },
+};
+
+pub const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+pub const PAISE_PER_RUPEE: f64 = 100.0;
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderKind {
+    /// The user pays INR through Razorpay and receives SOL from the treasury.
+    OnRamp,
+    /// The user sends SOL to the treasury and receives INR through a Razorpay payout.
+    OffRamp,
+}
+
+/// Progress of an order. Both flows go through `Created`, `Paid`, `Sending` and `Settled`.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum OrderState {
+    /// Waiting for the user to pay, in INR on-ramp and in SOL off-ramp.
+    Created,
+    /// The user paid, the canister owes the other leg.
+    Paid,
+    /// The canister is paying the other leg. On-ramp, a treasury transfer was signed and may
+    /// land; its signature is recorded. Off-ramp, a payout was requested and awaits processing.
+    Sending,
+    /// The payout of an off-ramp order failed and the SOL deposit is being returned. A treasury
+    /// transfer back to the wallet may have been signed and may land.
+    Refunding,
+    /// Both legs of the flow went through.
+    Settled,
+    /// The flow stopped before the user paid, see the reason.
+    Failed(String),
+    /// The payment of the user was returned, in INR on-ramp and in SOL off-ramp.
+    Refunded,
+}
+
+impl OrderState {
+    /// Whether the order can't progress anymore.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Settled | Self::Failed(_) | Self::Refunded)
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Order {
+    pub id: u64,
+    /// The principal that created the order.
+    pub owner: Principal,
+    pub kind: OrderKind,
+    pub state: OrderState,
+    /// Solana address of the user, receiving SOL on-ramp and sending it off-ramp.
+    pub wallet: String,
+    /// Amount in paise.
+    pub inr_amount: u64,
+    pub lamports: u64,
+    /// Price of one SOL in INR, implied by the amounts of the order.
+    pub sol_inr_rate: Option<f64>,
+    /// The quote the order was created from.
+    pub quote_id: Option<u64>,
+    /// Mid-market price of one SOL in INR when the order was quoted, which settlement is checked
+    /// against, see [`crate::quotes::slippage_exceeded`].
+    pub quoted_mid_rate: Option<f64>,
+    /// Key an off-ramp deposit must reference, identifying the order on chain.
+    pub reference: Option<String>,
+    pub razorpay_order_id: Option<String>,
+    /// The captured payment of an on-ramp order.
+    pub razorpay_payment_id: Option<String>,
+    pub razorpay_payout_id: Option<String>,
+    pub solana_signature: Option<String>,
+    /// The last signed treasury transfer of the order, kept to re-broadcast it.
+    pub signed_transaction: Option<String>,
+    /// Number of treasury transfers signed for the order.
+    pub send_attempts: u32,
+    /// Nanoseconds since the epoch.
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl Order {
+    fn update_rate(&mut self) {
+        self.sol_inr_rate = (self.inr_amount > 0 && self.lamports > 0).then(|| {
+            (self.inr_amount as f64 / PAISE_PER_RUPEE) / (self.lamports as f64 / LAMPORTS_PER_SOL)
+        });
+    }
+
+    /// Sets the amounts of both legs of the order and the rate they imply.
+    pub fn set_amounts(&mut self, inr_amount: u64, lamports: u64) {
+        self.inr_amount = inr_amount;
+        self.lamports = lamports;
+        self.update_rate();
+    }
+}
+
+impl Storable for Order {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode order"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("Failed to decode order")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Records a new order for the amounts of a quote, see [`crate::quotes::take`].
+pub fn create(owner: Principal, wallet: String, quote: &Quote) -> Order {
+    let id = NEXT_ORDER_ID.with(|next_id| {
+        let mut next_id = next_id.borrow_mut();
+        let id = *next_id.get();
+        next_id
+            .set(id + 1)
+            .expect("Failed to increment the order id counter");
+        id
+    });
+    let now = ic_cdk::api::time();
+
+    let mut order = Order {
+        id,
+        owner,
+        kind: quote.kind,
+        state: OrderState::Created,
+        wallet,
+        inr_amount: 0,
+        lamports: 0,
+        sol_inr_rate: None,
+        quote_id: Some(quote.id),
+        quoted_mid_rate: Some(quote.mid_rate),
+        reference: None,
+        razorpay_order_id: None,
+        razorpay_payment_id: None,
+        razorpay_payout_id: None,
+        solana_signature: None,
+        signed_transaction: None,
+        send_attempts: 0,
+        created_at: now,
+        updated_at: now,
+    };
+    order.set_amounts(quote.inr_paise, quote.sol_lamports);
+
+    ORDERS.with(|orders| orders.borrow_mut().insert(id, order.clone()));
+    ORDERS_BY_OWNER.with(|index| index.borrow_mut().insert((principal_key(&owner), id), ()));
+    ACTIVE_ORDERS.with(|active| active.borrow_mut().insert(id, ()));
+    order
+}
+
+pub fn get(id: u64) -> Option<Order> {
+    ORDERS.with(|orders| orders.borrow().get(&id))
+}
+
+/// Applies `f` to the order and stores the result.
+pub fn update(id: u64, f: impl FnOnce(&mut Order)) -> Result<Order, String> {
+    ORDERS.with(|orders| {
+        let mut orders = orders.borrow_mut();
+        let mut order = orders
+            .get(&id)
+            .ok_or_else(|| format!("Order {id} not found"))?;
+        f(&mut order);
+        order.updated_at = ic_cdk::api::time();
+        orders.insert(id, order.clone());
+        ACTIVE_ORDERS.with(|active| {
+            if order.state.is_final() {
+                active.borrow_mut().remove(&id);
+            } else {
+                active.borrow_mut().insert(id, ());
+            }
+        });
+        ORDERS_BY_RAZORPAY_ID.with(|index| {
+            let mut index = index.borrow_mut();
+            for razorpay_id in [&order.razorpay_order_id, &order.razorpay_payout_id]
+                .into_iter()
+                .flatten()
+            {
+                index.insert(razorpay_id.clone(), id);
+            }
+        });
+        Ok(order)
+    })
+}
+
+/// Returns the order with the given Razorpay order or payout id.
+pub fn find_by_razorpay_id(razorpay_id: &str) -> Option<Order> {
+    ORDERS_BY_RAZORPAY_ID
+        .with(|index| index.borrow().get(&razorpay_id.to_string()))
+        .and_then(get)
+}
+
+/// Returns the orders of a user, oldest first.
+pub fn list_by_owner(owner: &Principal) -> Vec<Order> {
+    let key = principal_key(owner);
+    let ids: Vec<u64> = ORDERS_BY_OWNER.with(|index| {
+        index
+            .borrow()
+            .range((key, 0)..=(key, u64::MAX))
+            .map(|((_, id), _)| id)
+            .collect()
+    });
+    ids.into_iter().filter_map(get).collect()
+}
+
+/// Returns the orders of the given kind that haven't reached a final state, oldest first.
+pub fn active(kind: OrderKind) -> Vec<Order> {
+    let ids: Vec<u64> =
+        ACTIVE_ORDERS.with(|active| active.borrow().iter().map(|(id, _)| id).collect());
+    ids.into_iter()
+        .filter_map(get)
+        .filter(|order| order.kind == kind)
+        .collect()
+}
@@ -0,0 +1,170 @@
+//! Razorpay contacts and fund accounts of users.
+//!
+//! A user gets a Razorpay contact on their first payout, referenced by their principal, and a
+//! fund account under it for each bank account, UPI address or card they are paid to. Their ids
+//! are kept, so later payouts reuse them instead of creating duplicates. Card numbers aren't
+//! kept: fund accounts are matched to cards by a key derived from a secret of the canister
+//! instead.
+
+use std::borrow::Cow;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64, Engine};
+use candid::{CandidType, Decode, Encode, Principal};
+use ic_cdk::api::management_canister::main::raw_rand;
+use ic_stable_structures::{storable::Bound, Storable};
+use razorpay::{ContactFilter, CreateContactRequest, CreateFundAccountRequest, PayoutArgs};
+use serde::Deserialize;
+
+use crate::{
+    razorpay_client,
+    state::{principal_key, DESTINATION_KEY_SECRET, PAYOUT_ACCOUNTS},
+};
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FundAccountRecord {
+    /// The destination paid out to, see [`razorpay::PayoutDestination::key`].
+    pub destination: String,
+    pub fund_account_id: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PayoutAccount {
+    pub contact_id: String,
+    /// The destinations the user was paid to.
+    pub fund_accounts: Vec<FundAccountRecord>,
+}
+
+impl Storable for PayoutAccount {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode payout account"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("Failed to decode payout account")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+pub fn get(owner: &Principal) -> Option<PayoutAccount> {
+    PAYOUT_ACCOUNTS.with(|accounts| accounts.borrow().get(&principal_key(owner)))
+}
+
+fn insert(owner: &Principal, account: PayoutAccount) {
+    PAYOUT_ACCOUNTS.with(|accounts| accounts.borrow_mut().insert(principal_key(owner), account));
+}
+
+/// Returns the secret keying payout destinations, generating it on first use.
+async fn destination_key_secret() -> Result<Vec<u8>, String> {
+    let secret = DESTINATION_KEY_SECRET.with(|secret| secret.borrow().get().clone());
+    if !secret.is_empty() {
+        return Ok(secret);
+    }
+
+    let (random,) = raw_rand()
+        .await
+        .map_err(|(code, message)| format!("Failed to generate a secret: {code:?} {message}"))?;
+    // Read again: another call may have generated the secret in the meantime.
+    DESTINATION_KEY_SECRET.with(|secret| {
+        let mut secret = secret.borrow_mut();
+        if secret.get().is_empty() {
+            secret
+                .set(random)
+                .map_err(|e| format!("Failed to store the secret: {e:?}"))?;
+        }
+        Ok(secret.get().clone())
+    })
+}
+
+/// Reference of the Razorpay contact of a user, to find it again if its id was lost. Razorpay
+/// limits references to 40 characters, so it is the unpadded base64 of the principal, which is at
+/// most 29 bytes long, rather than its textual form.
+fn contact_reference(owner: &Principal) -> String {
+    BASE64.encode(owner.as_slice())
+}
+
+/// Returns the fund account paying out to the destination in `args`, creating it and the
+/// contact of the user on first use.
+pub async fn fund_account_id(owner: &Principal, args: &PayoutArgs) -> Result<String, String> {
+    let client = razorpay_client()?;
+
+    let account = match get(owner) {
+        Some(account) => account,
+        None => {
+            let reference_id = contact_reference(owner);
+            let filter = ContactFilter {
+                reference_id: Some(reference_id.clone()),
+                active: Some(true),
+                ..Default::default()
+            };
+            let contact = match client.list_contacts(&filter).await?.into_iter().next() {
+                Some(contact) => contact,
+                None => {
+                    client
+                        .create_contact(&CreateContactRequest {
+                            name: args.name.clone(),
+                            email: args.email.clone(),
+                            contact: args.contact.clone(),
+                            reference_id: Some(reference_id),
+                        })
+                        .await?
+                }
+            };
+            // Read again: another call may have stored a contact in the meantime. Its fund
+            // accounts are kept, and the contact found or created here is left unused.
+            match get(owner) {
+                Some(account) => account,
+                None => {
+                    let account = PayoutAccount {
+                        contact_id: contact.id,
+                        fund_accounts: vec![],
+                    };
+                    insert(owner, account.clone());
+                    account
+                }
+            }
+        }
+    };
+
+    let destination = args.destination.key(&destination_key_secret().await?);
+    let existing = account
+        .fund_accounts
+        .iter()
+        .find(|record| record.destination == destination);
+    if let Some(record) = existing {
+        return Ok(record.fund_account_id.clone());
+    }
+
+    let fund_account = client
+        .create_fund_account(&CreateFundAccountRequest {
+            contact_id: account.contact_id,
+            details: args.destination.fund_account_details(),
+        })
+        .await?;
+
+    // Read again: the account may have changed while the fund account was created.
+    let mut account = get(owner).ok_or("Payout account not found")?;
+    account.fund_accounts.push(FundAccountRecord {
+        destination,
+        fund_account_id: fund_account.id.clone(),
+    });
+    insert(owner, account);
+    Ok(fund_account.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contact_reference() {
+        let owner = Principal::from_slice(&[0xff; 29]);
+        let reference = contact_reference(&owner);
+        assert_eq!(reference.len(), 39);
+        assert_eq!(BASE64.decode(reference).unwrap(), owner.as_slice());
+        assert_ne!(
+            contact_reference(&Principal::anonymous()),
+            contact_reference(&Principal::management_canister())
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Cache of the SOL price, refreshed by a timer.
+//!
+//! Fetching the price from the [`crate::price_oracle`] takes several HTTP outcalls, which cost
+//! cycles and seconds of latency. A timer fetches it once a minute instead, and keeps the last
+//! [`HISTORY_LEN`] prices so queries can read them for free. Callers needing a price only fetch
+//! it themselves when the cache is stale, for instance after the timer failed for a while.
+//!
+//! The cache lives on the heap: it is refilled within a minute of an upgrade.
+
+use std::{cell::RefCell, collections::VecDeque, time::Duration};
+
+use crate::price_oracle::{self, SolPrice};
+
+/// How often the price is refreshed.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of prices kept, one per minute.
+pub const HISTORY_LEN: usize = 60;
+
+/// Cached prices older than this, in seconds, are stale. Prices are for the last closed minute,
+/// so fresh ones are one to two minutes old.
+pub const MAX_PRICE_AGE: u64 = 3 * 60;
+
+thread_local! {
+    /// Cached prices, oldest first.
+    static PRICES: RefCell<VecDeque<SolPrice>> = RefCell::new(VecDeque::with_capacity(HISTORY_LEN));
+}
+
+/// Starts refreshing the price periodically, and right away. Timers don't survive upgrades, so
+/// this runs on init and post-upgrade.
+pub fn schedule() {
+    ic_cdk_timers::set_timer(Duration::ZERO, || ic_cdk::spawn(refresh()));
+    ic_cdk_timers::set_timer_interval(REFRESH_INTERVAL, || ic_cdk::spawn(refresh()));
+}
+
+async fn refresh() {
+    let now = now_secs();
+    if latest().is_some_and(|price| price.timestamp == price_oracle::closed_minute(now)) {
+        return;
+    }
+    if let Err(err) = fetch(now).await {
+        ic_cdk::api::print(format!("Failed to refresh the SOL price: {err}"));
+    }
+}
+
+/// Returns the latest cached price, fresh or not.
+pub fn latest() -> Option<SolPrice> {
+    PRICES.with(|prices| prices.borrow().back().copied())
+}
+
+/// Returns the cached prices, oldest first.
+pub fn history() -> Vec<SolPrice> {
+    PRICES.with(|prices| prices.borrow().iter().copied().collect())
+}
+
+/// Returns the latest price, from the cache unless it is stale.
+pub async fn sol_price() -> Result<SolPrice, String> {
+    let now = now_secs();
+    match latest() {
+        Some(price) if now.saturating_sub(price.timestamp) <= MAX_PRICE_AGE => Ok(price),
+        _ => fetch(now).await,
+    }
+}
+
+async fn fetch(now: u64) -> Result<SolPrice, String> {
+    let price = price_oracle::get_sol_price(now).await?;
+    record(price);
+    Ok(price)
+}
+
+/// Adds a price to the cache, unless a price as recent is already cached.
+fn record(price: SolPrice) {
+    PRICES.with(|prices| {
+        let mut prices = prices.borrow_mut();
+        if prices
+            .back()
+            .is_some_and(|latest| latest.timestamp >= price.timestamp)
+        {
+            return;
+        }
+        if prices.len() == HISTORY_LEN {
+            prices.pop_front();
+        }
+        prices.push_back(price);
+    });
+}
+
+fn now_secs() -> u64 {
+    ic_cdk::api::time() / 1_000_000_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(timestamp: u64) -> SolPrice {
+        SolPrice {
+            price: 57.0,
+            sources: 3,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_record_keeps_recent_prices() {
+        for minute in 0..HISTORY_LEN as u64 + 5 {
+            record(price(minute * 60));
+        }
+        record(price(60));
+
+        let history = history();
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history[0].timestamp, 5 * 60);
+        assert_eq!(latest(), Some(price((HISTORY_LEN as u64 + 4) * 60)));
+    }
+}
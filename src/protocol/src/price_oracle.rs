@@ -0,0 +1,349 @@
+//! SOL price oracle.
+//!
+//! The price is queried from several exchanges through HTTP outcalls. Replicas fetch each
+//! response independently and must agree on it, so a transform function reduces every response
+//! to the candle of one closed minute, dropping headers and anything else that differs between
+//! fetches. Stale candles and prices far from the others are discarded, and the median of the
+//! remaining ones is returned.
+
+use candid::CandidType;
+use ic_cdk::api::management_canister::http_request::{
+    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
+    TransformContext,
+};
+use ic_solana::outcalls::http_request_cycles;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Candles older than this, in seconds, are discarded.
+pub const MAX_CANDLE_AGE: u64 = 10 * 60;
+
+/// Prices deviating from the median by more than this fraction are discarded.
+pub const MAX_DEVIATION: f64 = 0.02;
+
+/// Number of sources a price must be confirmed by.
+pub const MIN_SOURCES: u32 = 2;
+
+/// Identifies this canister to the exchanges.
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceSource {
+    Coinbase,
+    Kraken,
+    Binance,
+    CoinGecko,
+}
+
+impl PriceSource {
+    pub const ALL: [Self; 4] = [Self::Coinbase, Self::Kraken, Self::Binance, Self::CoinGecko];
+
+    /// URL of the SOL/USD candles around `minute`, a Unix timestamp in seconds.
+    fn url(self, minute: u64) -> String {
+        match self {
+            Self::Coinbase => format!(
+                "https://api.exchange.coinbase.com/products/SOL-USD/candles?start={}&end={minute}&granularity=60",
+                minute - 60
+            ),
+            Self::Kraken => format!(
+                "https://api.kraken.com/0/public/OHLC?pair=SOLUSD&interval=1&since={}",
+                minute - 120
+            ),
+            Self::Binance => format!(
+                "https://api.binance.com/api/v3/klines?symbol=SOLUSDT&interval=1m&startTime={}&endTime={}",
+                (minute - 60) * 1000,
+                minute * 1000
+            ),
+            Self::CoinGecko => format!(
+                "https://api.coingecko.com/api/v3/coins/solana/market_chart/range?vs_currency=usd&from={}&to={minute}",
+                minute - MAX_CANDLE_AGE
+            ),
+        }
+    }
+
+    /// Response size limit, headers included. The candles requested take a few hundred bytes,
+    /// and the headers about as much as a kilobyte.
+    fn max_response_bytes(self) -> u64 {
+        match self {
+            Self::Coinbase | Self::Kraken | Self::Binance => 4 * 1024,
+            // Market caps and volumes come along with the prices.
+            Self::CoinGecko => 8 * 1024,
+        }
+    }
+
+    /// Parses a response into the latest candle that opened at or before `minute`.
+    pub fn parse(self, body: &[u8], minute: u64) -> Option<Candle> {
+        let value: Value = serde_json::from_slice(body).ok()?;
+        let candle = |timestamp: u64, [open, high, low, close]: [f64; 4]| Candle {
+            source: self,
+            timestamp,
+            open,
+            high,
+            low,
+            close,
+        };
+
+        let candles: Vec<Candle> = match self {
+            // [time, low, high, open, close, volume], newest first.
+            Self::Coinbase => value
+                .as_array()?
+                .iter()
+                .filter_map(|row| {
+                    let field = |i: usize| row.get(i)?.as_f64();
+                    Some(candle(
+                        row.get(0)?.as_u64()?,
+                        [field(3)?, field(2)?, field(1)?, field(4)?],
+                    ))
+                })
+                .collect(),
+            // [time, "open", "high", "low", "close", "vwap", "volume", count], oldest first.
+            Self::Kraken => value["result"]["SOLUSD"]
+                .as_array()?
+                .iter()
+                .filter_map(|row| {
+                    let field = |i: usize| row.get(i)?.as_str()?.parse().ok();
+                    Some(candle(
+                        row.get(0)?.as_u64()?,
+                        [field(1)?, field(2)?, field(3)?, field(4)?],
+                    ))
+                })
+                .collect(),
+            // [open time in ms, "open", "high", "low", "close", ...], oldest first.
+            Self::Binance => value
+                .as_array()?
+                .iter()
+                .filter_map(|row| {
+                    let field = |i: usize| row.get(i)?.as_str()?.parse().ok();
+                    Some(candle(
+                        row.get(0)?.as_u64()? / 1000,
+                        [field(1)?, field(2)?, field(3)?, field(4)?],
+                    ))
+                })
+                .collect(),
+            // {"prices": [[time in ms, price], ...]}, oldest first.
+            Self::CoinGecko => value["prices"]
+                .as_array()?
+                .iter()
+                .filter_map(|row| {
+                    let price = row.get(1)?.as_f64()?;
+                    Some(candle(row.get(0)?.as_f64()? as u64 / 1000, [price; 4]))
+                })
+                .collect(),
+        };
+
+        candles
+            .into_iter()
+            .filter(|candle| candle.timestamp <= minute && candle.close > 0.0)
+            .max_by_key(|candle| candle.timestamp)
+    }
+}
+
+/// Price of SOL in USD over a period starting at `timestamp`, a Unix timestamp in seconds.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Candle {
+    pub source: PriceSource,
+    pub timestamp: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct SolPrice {
+    /// Median of the closing prices of the sources, in USD.
+    pub price: f64,
+    /// Number of sources the price is aggregated from.
+    pub sources: u32,
+    /// The minute the price is for, as a Unix timestamp in seconds.
+    pub timestamp: u64,
+}
+
+/// Context of the transform of a response, identifying how to parse it.
+#[derive(Serialize, Deserialize)]
+struct TransformArgsContext {
+    source: PriceSource,
+    minute: u64,
+}
+
+/// Returns the price of SOL in USD over the last closed minute before `timestamp`, a Unix
+/// timestamp in seconds.
+pub async fn get_sol_price(timestamp: u64) -> Result<SolPrice, String> {
+    let minute = closed_minute(timestamp);
+    let candles = futures::future::join_all(
+        PriceSource::ALL
+            .into_iter()
+            .map(|source| fetch_candle(source, minute)),
+    )
+    .await;
+
+    let mut closes = vec![];
+    for candle in candles {
+        match candle {
+            Ok(candle) if minute.saturating_sub(candle.timestamp) <= MAX_CANDLE_AGE => {
+                closes.push(candle.close)
+            }
+            Ok(candle) => ic_cdk::api::print(format!(
+                "Discarding stale {:?} candle of {}",
+                candle.source, candle.timestamp
+            )),
+            Err(e) => ic_cdk::api::print(e),
+        }
+    }
+
+    aggregate(closes, minute)
+}
+
+/// Returns the last closed minute before `timestamp`, a Unix timestamp in seconds.
+pub fn closed_minute(timestamp: u64) -> u64 {
+    timestamp / 60 * 60 - 60
+}
+
+/// Returns the median of the prices, after discarding those too far from the median of all.
+pub fn aggregate(prices: Vec<f64>, minute: u64) -> Result<SolPrice, String> {
+    let median_of_all = median(prices.clone()).ok_or("No price source responded")?;
+    let prices: Vec<f64> = prices
+        .into_iter()
+        .filter(|price| ((price - median_of_all) / median_of_all).abs() <= MAX_DEVIATION)
+        .collect();
+
+    let sources = prices.len() as u32;
+    if sources < MIN_SOURCES {
+        return Err(format!(
+            "Only {sources} price sources agree, {MIN_SOURCES} are required"
+        ));
+    }
+    Ok(SolPrice {
+        price: median(prices).expect("prices are not empty"),
+        sources,
+        timestamp: minute,
+    })
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    match values.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((values[middle - 1] + values[middle]) / 2.0),
+        _ => Some(values[middle]),
+    }
+}
+
+async fn fetch_candle(source: PriceSource, minute: u64) -> Result<Candle, String> {
+    let context = TransformArgsContext { source, minute };
+    let headers = vec![HttpHeader {
+        name: "User-Agent".to_string(),
+        value: USER_AGENT.to_string(),
+    }];
+    let url = source.url(minute);
+    let max_response_bytes = source.max_response_bytes();
+    let request_bytes = url.len() + "User-Agent".len() + USER_AGENT.len();
+    let cycles = http_request_cycles(request_bytes as u64, max_response_bytes);
+
+    let request = CanisterHttpRequestArgument {
+        url,
+        max_response_bytes: Some(max_response_bytes),
+        method: HttpMethod::GET,
+        headers,
+        body: None,
+        transform: Some(TransformContext::from_name(
+            "transform_price_response".to_string(),
+            serde_json::to_vec(&context).expect("context serializes to JSON"),
+        )),
+    };
+
+    let (response,) = http_request(request, cycles)
+        .await
+        .map_err(|(code, message)| {
+            format!("Failed to fetch {source:?} price: {code:?} {message}")
+        })?;
+    serde_json::from_slice(&response.body)
+        .map_err(|_| format!("{source:?} returned no price, status {}", response.status))
+}
+
+/// Reduces a price response to its candle, serialized to JSON, or to an empty body if it has
+/// none.
+pub fn transform_price_response(args: TransformArgs) -> HttpResponse {
+    let candle = serde_json::from_slice::<TransformArgsContext>(&args.context)
+        .ok()
+        .and_then(|context| context.source.parse(&args.response.body, context.minute));
+
+    HttpResponse {
+        status: args.response.status,
+        headers: vec![],
+        body: candle
+            .map(|candle| serde_json::to_vec(&candle).expect("candles serialize to JSON"))
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 1_700_000_040;
+
+    #[test]
+    fn test_parse_coinbase() {
+        let body = br#"[
+            [1700000040, 56.5, 57.5, 57.0, 57.25, 1200.5],
+            [1699999980, 56.0, 57.0, 56.5, 57.0, 1000.0]
+        ]"#;
+        let candle = PriceSource::Coinbase.parse(body, MINUTE).unwrap();
+        assert_eq!(candle.timestamp, MINUTE);
+        assert_eq!((candle.open, candle.high, candle.low), (57.0, 57.5, 56.5));
+        assert_eq!(candle.close, 57.25);
+    }
+
+    #[test]
+    fn test_parse_kraken() {
+        let body = br#"{"error": [], "result": {"SOLUSD": [
+            [1699999980, "56.50", "57.00", "56.00", "57.00", "56.80", "10.0", 5],
+            [1700000040, "57.00", "57.50", "56.50", "57.25", "57.10", "12.0", 7],
+            [1700000100, "57.25", "58.00", "57.00", "58.00", "57.50", "3.0", 2]
+        ], "last": 1700000040}}"#;
+        let candle = PriceSource::Kraken.parse(body, MINUTE).unwrap();
+        assert_eq!(candle.timestamp, MINUTE);
+        assert_eq!(candle.close, 57.25);
+    }
+
+    #[test]
+    fn test_parse_binance() {
+        let body = br#"[
+            [1700000040000, "57.00", "57.50", "56.50", "57.25", "1200.5", 1700000099999, "0", 10, "0", "0", "0"]
+        ]"#;
+        let candle = PriceSource::Binance.parse(body, MINUTE).unwrap();
+        assert_eq!(candle.timestamp, MINUTE);
+        assert_eq!(candle.close, 57.25);
+    }
+
+    #[test]
+    fn test_parse_coingecko() {
+        let body = br#"{"prices": [[1699999800123, 56.9], [1700000010456, 57.25]]}"#;
+        let candle = PriceSource::CoinGecko.parse(body, MINUTE).unwrap();
+        assert_eq!(candle.timestamp, 1_700_000_010);
+        assert_eq!(candle.close, 57.25);
+    }
+
+    #[test]
+    fn test_parse_invalid_response() {
+        let body = br#"{"message": "Too many requests"}"#;
+        for source in PriceSource::ALL {
+            assert_eq!(source.parse(body, MINUTE), None);
+        }
+    }
+
+    #[test]
+    fn test_aggregate_discards_outliers() {
+        let price = aggregate(vec![57.0, 57.2, 57.1, 80.0], MINUTE).unwrap();
+        assert_eq!(price.price, 57.1);
+        assert_eq!(price.sources, 3);
+
+        let price = aggregate(vec![57.0, 57.5], MINUTE).unwrap();
+        assert_eq!(price.price, 57.25);
+        assert!(aggregate(vec![57.0], MINUTE).is_err());
+        assert!(aggregate(vec![57.0, 80.0], MINUTE).is_err());
+        assert!(aggregate(vec![], MINUTE).is_err());
+    }
+}
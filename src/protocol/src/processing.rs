@@ -0,0 +1,60 @@
+//! Timer driving active orders, see [`crate::onramp`] and [`crate::offramp`].
+//!
+//! Both flows send SOL from the treasury with the same durable nonce, so their orders are
+//! processed one at a time by a single run.
+
+use std::{cell::Cell, time::Duration};
+
+use crate::{
+    offramp, onramp,
+    orders::{self, OrderKind},
+};
+
+/// How often active orders are processed.
+pub const PROCESS_INTERVAL: Duration = Duration::from_secs(30);
+
+thread_local! {
+    static PROCESSING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Prevents overlapping runs of [`process_orders`]. Released on drop, including when a call
+/// traps and its future is cleaned up.
+struct ProcessingGuard;
+
+impl ProcessingGuard {
+    fn acquire() -> Option<Self> {
+        (!PROCESSING.with(|processing| processing.replace(true))).then_some(Self)
+    }
+}
+
+impl Drop for ProcessingGuard {
+    fn drop(&mut self) {
+        PROCESSING.with(|processing| processing.set(false));
+    }
+}
+
+/// Starts processing active orders periodically. Timers don't survive upgrades, so this runs
+/// on init and post-upgrade.
+pub fn schedule() {
+    ic_cdk_timers::set_timer_interval(PROCESS_INTERVAL, || ic_cdk::spawn(process_orders()));
+}
+
+/// Advances every active order by at most one step.
+pub async fn process_orders() {
+    let Some(_guard) = ProcessingGuard::acquire() else {
+        return;
+    };
+
+    for order in orders::active(OrderKind::OnRamp) {
+        let id = order.id;
+        if let Err(err) = onramp::process_order(order).await {
+            ic_cdk::api::print(format!("Failed to process order {id}: {err}"));
+        }
+    }
+    for order in orders::active(OrderKind::OffRamp) {
+        let id = order.id;
+        if let Err(err) = offramp::process_order(order).await {
+            ic_cdk::api::print(format!("Failed to process order {id}: {err}"));
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! SOL/INR quotes.
+//!
+//! The mid-market INR price of SOL combines the cached SOL/USD price of the [`crate::price_feed`]
+//! with the USD/INR rate of the exchange rate canister. A quote applies the configured spread
+//! against the user, and the flat fee, rounding every amount in favor of the treasury.
+//!
+//! Quotes are stored with an id, and orders are created from an unexpired quote of their owner,
+//! see [`take`], so users are held to the amounts they agreed to. Each quote is used at most
+//! once and expires after [`QUOTE_TTL`]. The market may still move between the quote and the
+//! settlement of its order; past the configured slippage, settlement is refused and the user is
+//! refunded, see [`slippage_exceeded`].
+
+use std::borrow::Cow;
+
+use candid::{CandidType, Decode, Encode, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::Deserialize;
+
+use crate::{
+    forex,
+    orders::{Order, OrderKind, LAMPORTS_PER_SOL, PAISE_PER_RUPEE},
+    price_feed,
+    state::{Memory, NEXT_QUOTE_ID, QUOTES, QUOTE_CONFIG},
+};
+
+/// How long a quote is valid, in nanoseconds.
+pub const QUOTE_TTL: u64 = 60 * 1_000_000_000;
+
+/// Spreads and slippages are in basis points, so 10 000 is 100%.
+const MAX_BPS: u32 = 10_000;
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct QuoteConfig {
+    /// Margin taken from the mid-market rate, in basis points: users buy SOL above it and sell
+    /// below it.
+    pub spread_bps: u32,
+    /// Flat fee per order, in paise.
+    pub fee_paise: u64,
+    /// How far, in basis points, the mid-market rate may move against the treasury between a
+    /// quote and the settlement of its order.
+    pub max_slippage_bps: u32,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        Self {
+            spread_bps: 100,
+            fee_paise: 0,
+            max_slippage_bps: 100,
+        }
+    }
+}
+
+impl Storable for QuoteConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode quote config"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("Failed to decode quote config")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// The amount a quote is requested for; the other one is quoted.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteAmount {
+    Lamports(u64),
+    InrPaise(u64),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Quote {
+    pub id: u64,
+    /// The principal the quote was given to, the only one that can order it.
+    pub owner: Principal,
+    /// On-ramp, the user buys SOL; off-ramp, they sell it.
+    pub kind: OrderKind,
+    pub sol_lamports: u64,
+    /// Paid by the user on-ramp, and paid out to them off-ramp, fee included.
+    pub inr_paise: u64,
+    /// Price of one SOL in INR, spread included.
+    pub rate: f64,
+    /// Mid-market price of one SOL in INR.
+    pub mid_rate: f64,
+    pub fee_paise: u64,
+    /// Nanoseconds since the epoch.
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+impl Storable for Quote {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode quote"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("Failed to decode quote")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+pub fn get_config() -> QuoteConfig {
+    QUOTE_CONFIG.with(|config| config.borrow().get().clone())
+}
+
+pub fn set_config(config: QuoteConfig) -> Result<(), String> {
+    if config.spread_bps >= MAX_BPS || config.max_slippage_bps >= MAX_BPS {
+        return Err(format!(
+            "Spread and slippage must be below {MAX_BPS} basis points"
+        ));
+    }
+    QUOTE_CONFIG
+        .with(|cell| cell.borrow_mut().set(config))
+        .map(|_| ())
+        .map_err(|e| format!("Failed to store quote config: {e:?}"))
+}
+
+/// Returns the mid-market price of one SOL in INR.
+pub async fn sol_inr_rate() -> Result<f64, String> {
+    let sol_usd = price_feed::sol_price().await?;
+    let usd_inr = forex::usd_inr_rate().await?;
+    Ok(sol_usd.price * usd_inr)
+}
+
+/// Quotes an order at the current rate for `owner`, and stores the quote until it expires.
+pub async fn get_quote(
+    owner: Principal,
+    kind: OrderKind,
+    amount: QuoteAmount,
+) -> Result<Quote, String> {
+    let mid_rate = sol_inr_rate().await?;
+    let now = ic_cdk::api::time();
+    let quote = compute_quote(kind, amount, mid_rate, &get_config(), now)?;
+
+    let id = NEXT_QUOTE_ID.with(|next_id| {
+        let mut next_id = next_id.borrow_mut();
+        let id = *next_id.get();
+        next_id
+            .set(id + 1)
+            .expect("Failed to increment the quote id counter");
+        id
+    });
+    let quote = Quote { id, owner, ..quote };
+    QUOTES.with(|quotes| {
+        let mut quotes = quotes.borrow_mut();
+        remove_expired(&mut quotes, now);
+        quotes.insert(id, quote.clone());
+    });
+    Ok(quote)
+}
+
+/// Removes the quotes that expired. Quotes expire in the order of their ids, as they all have the
+/// same TTL.
+fn remove_expired(quotes: &mut StableBTreeMap<u64, Quote, Memory>, now: u64) {
+    while let Some((id, quote)) = quotes.first_key_value() {
+        if quote.expires_at > now {
+            break;
+        }
+        quotes.remove(&id);
+    }
+}
+
+/// Removes a quote of `owner` to create an order of `kind` from it. Fails if the quote expired or
+/// was already used.
+pub fn take(quote_id: u64, owner: Principal, kind: OrderKind) -> Result<Quote, String> {
+    QUOTES.with(|quotes| {
+        let mut quotes = quotes.borrow_mut();
+        let quote = quotes
+            .get(&quote_id)
+            .filter(|quote| quote.expires_at > ic_cdk::api::time())
+            .ok_or_else(|| format!("Quote {quote_id} expired or was already used"))?;
+        if quote.owner != owner {
+            return Err(format!("Quote {quote_id} belongs to another user"));
+        }
+        if quote.kind != kind {
+            return Err(format!("Quote {quote_id} is for an {:?} order", quote.kind));
+        }
+        quotes.remove(&quote_id);
+        Ok(quote)
+    })
+}
+
+/// Whether the mid-market rate moved against the treasury by more than the configured slippage
+/// since the order was quoted, in which case it must not settle. Fails if the rate can't be
+/// fetched. Orders created before quotes were locked aren't checked.
+pub async fn slippage_exceeded(order: &Order) -> Result<bool, String> {
+    let Some(quoted_rate) = order.quoted_mid_rate else {
+        return Ok(false);
+    };
+    let rate = sol_inr_rate().await?;
+    let max_slippage_bps = get_config().max_slippage_bps;
+    if !exceeds_slippage(order.kind, quoted_rate, rate, max_slippage_bps) {
+        return Ok(false);
+    }
+    ic_cdk::api::print(format!(
+        "SOL/INR moved from {quoted_rate} to {rate} since order {} was quoted, beyond the \
+         {max_slippage_bps} basis points allowed",
+        order.id
+    ));
+    Ok(true)
+}
+
+/// Whether the rate moved from `quoted_rate` to `rate` against the treasury by more than
+/// `max_slippage_bps`: up when it sells SOL on-ramp, down when it buys SOL off-ramp.
+pub fn exceeds_slippage(
+    kind: OrderKind,
+    quoted_rate: f64,
+    rate: f64,
+    max_slippage_bps: u32,
+) -> bool {
+    let slippage = (rate - quoted_rate) / quoted_rate;
+    let max_slippage = max_slippage_bps as f64 / MAX_BPS as f64;
+    match kind {
+        OrderKind::OnRamp => slippage > max_slippage,
+        OrderKind::OffRamp => -slippage > max_slippage,
+    }
+}
+
+/// Quotes an order at the mid-market rate `mid_rate`, in INR per SOL. The quote isn't stored, and
+/// has no id or owner yet.
+pub fn compute_quote(
+    kind: OrderKind,
+    amount: QuoteAmount,
+    mid_rate: f64,
+    config: &QuoteConfig,
+    now: u64,
+) -> Result<Quote, String> {
+    let spread = config.spread_bps as f64 / MAX_BPS as f64;
+    let rate = match kind {
+        OrderKind::OnRamp => mid_rate * (1.0 + spread),
+        OrderKind::OffRamp => mid_rate * (1.0 - spread),
+    };
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(format!("Invalid SOL/INR rate {rate}"));
+    }
+    let paise_per_lamport = rate * PAISE_PER_RUPEE / LAMPORTS_PER_SOL;
+    let fee = config.fee_paise;
+    let too_small = || "Amount is too small to cover the fee".to_string();
+
+    let (sol_lamports, inr_paise) = match (kind, amount) {
+        (OrderKind::OnRamp, QuoteAmount::Lamports(lamports)) => {
+            let paise = (lamports as f64 * paise_per_lamport).ceil() as u64;
+            (lamports, paise.saturating_add(fee))
+        }
+        (OrderKind::OnRamp, QuoteAmount::InrPaise(paise)) => {
+            let net = paise.checked_sub(fee).ok_or_else(too_small)?;
+            ((net as f64 / paise_per_lamport).floor() as u64, paise)
+        }
+        (OrderKind::OffRamp, QuoteAmount::Lamports(lamports)) => {
+            let paise = (lamports as f64 * paise_per_lamport).floor() as u64;
+            (lamports, paise.checked_sub(fee).ok_or_else(too_small)?)
+        }
+        (OrderKind::OffRamp, QuoteAmount::InrPaise(paise)) => {
+            let gross = paise.saturating_add(fee);
+            ((gross as f64 / paise_per_lamport).ceil() as u64, paise)
+        }
+    };
+    if sol_lamports == 0 || inr_paise == 0 {
+        return Err(too_small());
+    }
+
+    Ok(Quote {
+        id: 0,
+        owner: Principal::anonymous(),
+        kind,
+        sol_lamports,
+        inr_paise,
+        rate,
+        mid_rate,
+        fee_paise: fee,
+        created_at: now,
+        expires_at: now + QUOTE_TTL,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: QuoteConfig = QuoteConfig {
+        spread_bps: 100,
+        fee_paise: 1_000,
+        max_slippage_bps: 100,
+    };
+
+    #[test]
+    fn test_quote_on_ramp() {
+        // 1 SOL at 10 000 INR, plus 1%, plus the 10 INR fee.
+        let quote = compute_quote(
+            OrderKind::OnRamp,
+            QuoteAmount::Lamports(1_000_000_000),
+            10_000.0,
+            &CONFIG,
+            0,
+        )
+        .unwrap();
+        assert_eq!(quote.inr_paise, 1_011_000);
+        assert_eq!(quote.rate, 10_100.0);
+        assert_eq!(quote.expires_at, QUOTE_TTL);
+
+        let quote = compute_quote(
+            OrderKind::OnRamp,
+            QuoteAmount::InrPaise(1_011_000),
+            10_000.0,
+            &CONFIG,
+            0,
+        )
+        .unwrap();
+        assert_eq!(quote.sol_lamports, 1_000_000_000);
+    }
+
+    #[test]
+    fn test_quote_off_ramp() {
+        // 1 SOL at 10 000 INR, minus 1%, minus the 10 INR fee.
+        let quote = compute_quote(
+            OrderKind::OffRamp,
+            QuoteAmount::Lamports(1_000_000_000),
+            10_000.0,
+            &CONFIG,
+            0,
+        )
+        .unwrap();
+        assert_eq!(quote.inr_paise, 989_000);
+
+        let quote = compute_quote(
+            OrderKind::OffRamp,
+            QuoteAmount::InrPaise(989_000),
+            10_000.0,
+            &CONFIG,
+            0,
+        )
+        .unwrap();
+        assert_eq!(quote.sol_lamports, 1_000_000_000);
+    }
+
+    #[test]
+    fn test_quote_below_fee() {
+        // Selling 1 000 lamports yields 0.99 paise, less than the fee.
+        assert!(compute_quote(
+            OrderKind::OffRamp,
+            QuoteAmount::Lamports(1_000),
+            10_000.0,
+            &CONFIG,
+            0
+        )
+        .is_err());
+        assert!(compute_quote(
+            OrderKind::OnRamp,
+            QuoteAmount::InrPaise(500),
+            10_000.0,
+            &CONFIG,
+            0
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_exceeds_slippage() {
+        assert!(!exceeds_slippage(
+            OrderKind::OnRamp,
+            10_000.0,
+            10_100.0,
+            100
+        ));
+        assert!(exceeds_slippage(OrderKind::OnRamp, 10_000.0, 10_101.0, 100));
+        assert!(!exceeds_slippage(OrderKind::OnRamp, 10_000.0, 5_000.0, 100));
+
+        assert!(!exceeds_slippage(
+            OrderKind::OffRamp,
+            10_000.0,
+            9_900.0,
+            100
+        ));
+        assert!(exceeds_slippage(OrderKind::OffRamp, 10_000.0, 9_899.0, 100));
+        assert!(!exceeds_slippage(
+            OrderKind::OffRamp,
+            10_000.0,
+            20_000.0,
+            100
+        ));
+    }
+}
@@ -1,63 +1,36 @@
 use std::str::FromStr;
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use borsh_derive::{BorshDeserialize, BorshSerialize};
-use ic_cdk::api::management_canister::http_request::{
-    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod,
-};
 use ic_solana::{
-    rpc_client::{ConsensusStrategy, RpcApi, RpcClient, RpcClientConfig},
-    system_instruction::transfer,
+    nonce::NONCE_ACCOUNT_LENGTH,
+    rpc_client::{
+        ConsensusStrategy, RpcApi, RpcClient, RpcClientConfig, RpcResult, RpcSendAndConfirmConfig,
+        TransactionConfirmation,
+    },
+    signer::Signer,
+    system_instruction::{create_nonce_account_with_seed, transfer},
     types::{
-        AccountMeta, BlockHash, Cluster, CommitmentLevel, Instruction, Message, Pubkey,
-        RpcContextConfig, RpcSendTransactionConfig, Transaction,
+        AccountMeta, BlockHash, Cluster, CommitmentConfig, CommitmentLevel, Instruction, Message,
+        Pubkey, RpcContextConfig, RpcSendTransactionConfig, RpcSignatureStatusConfig,
+        RpcTransactionConfig, Signature, Transaction, UiTransactionEncoding, VersionedTransaction,
     },
 };
-use serde_json::Value;
 
-pub const SOLANA_TREASURY_ADDRESS: &str = "29HY6PqigFtdgyBTiiQQ5XCd3MxrXWSFtMWysqgHK5eJ";
+use crate::treasury::{treasury_nonce_address, treasury_signer, TREASURY_NONCE_SEED};
 
 const INITIALIZE_DISCRIMINATOR: [u8; 8] = [175, 175, 109, 31, 13, 152, 155, 237];
 
-pub async fn get_sol_price(timestamp: u64) -> Result<f64, String> {
-    ic_cdk::api::print(format!("fetching price at {}", timestamp));
-    let headers = vec![HttpHeader {
-        name: "User-Agent".to_string(),
-        value: "exchange_rate_canister".to_string(),
-    }];
-    let url = format!(
-        "https://api.exchange.coinbase.com/products/SOL-USD/candles?start={}&end={}&granularity={}",
-        (timestamp.checked_sub(60u64).unwrap()).to_string(),
-        (timestamp.checked_add(60u64).unwrap()).to_string(),
-        60.to_string()
-    );
-
-    let request = CanisterHttpRequestArgument {
-        url,
-        max_response_bytes: None,
-        method: HttpMethod::GET,
-        headers,
-        body: None,
-        transform: None,
-    };
+/// A system transfer uses 150 compute units, plus 150 for each compute budget instruction.
+const TRANSFER_COMPUTE_UNIT_LIMIT: u32 = 450;
 
-    let response = http_request(request, 1_603_114_800).await;
+/// Compute units used by the `AdvanceNonceAccount` instruction of durable nonce transactions.
+const ADVANCE_NONCE_COMPUTE_UNITS: u32 = 150;
 
-    match response {
-        Ok(response) => {
-            let response_body = String::from_utf8(response.0.body).unwrap();
-            ic_cdk::api::print(response_body.clone());
-            let response_array: Value = serde_json::from_str(response_body.as_str()).unwrap();
-            let array = &response_array[1];
-
-            Ok(array[4].as_f64().unwrap())
-        }
-        Err(err) => Err(format!("Error creating order: {}", err.1)),
-    }
-}
-
-pub async fn transfer_sol(from: String, to: String, amount: u64, keypair: [u8; 64]) -> String {
-    let from = Pubkey::from_str(from.as_str()).expect("Invalid pubkey");
+/// Percentile of the recent prioritization fees paid for transfers.
+const PRIORITY_FEE_PERCENTILE: u8 = 75;
 
+fn rpc_client() -> RpcClient {
     let cluster = Cluster::Localnet;
     let rpc = RpcApi::new(cluster.url());
     ic_cdk::api::print(cluster.url());
@@ -71,125 +44,381 @@ pub async fn transfer_sol(from: String, to: String, amount: u64, keypair: [u8; 6
         is_demo_active: true,
     };
 
+    RpcClient::new(vec![rpc], Some(config))
+}
+
+fn send_transaction_config() -> RpcSendTransactionConfig {
+    RpcSendTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        skip_preflight: false,
+        preflight_commitment: Some(CommitmentLevel::Finalized),
+        max_retries: None,
+        min_context_slot: None,
+    }
+}
+
+/// Returns the latest finalized blockhash, along with the last block height at which
+/// transactions signed with it can land.
+async fn latest_blockhash(client: &RpcClient) -> Result<(BlockHash, u64), String> {
     let context_config = RpcContextConfig {
         commitment: Some(CommitmentLevel::Finalized),
         min_context_slot: None,
     };
 
-    let client = RpcClient::new(vec![rpc], Some(config));
+    let latest_blockhash = client
+        .get_latest_blockhash(Some(context_config))
+        .await
+        .map_err(|e| format!("Failed to fetch the latest blockhash: {e}"))?
+        .parse_value();
+
+    let blockhash = BlockHash::from_str(latest_blockhash.blockhash.as_str())
+        .map_err(|e| format!("Invalid blockhash {}: {e}", latest_blockhash.blockhash))?;
+    Ok((blockhash, latest_blockhash.last_valid_block_height))
+}
 
-    let rpc_transaction_config = RpcSendTransactionConfig {
-        encoding: Some(ic_solana::types::UiTransactionEncoding::Base64),
-        skip_preflight: false,
-        preflight_commitment: Some(CommitmentLevel::Finalized),
-        max_retries: None,
-        min_context_slot: None,
+/// Returns the signature of a confirmed transaction, or why it didn't land.
+fn confirmation_result(confirmation: RpcResult<TransactionConfirmation>) -> Result<String, String> {
+    match confirmation {
+        Ok(TransactionConfirmation::Confirmed { signature, .. }) => Ok(signature.to_string()),
+        Ok(TransactionConfirmation::Failed { signature, err }) => {
+            Err(format!("Transaction {signature} failed: {err}"))
+        }
+        Ok(TransactionConfirmation::Expired { signature }) => Err(format!(
+            "Transaction {signature} expired before it was processed"
+        )),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Fetches a finalized transaction, failing if it isn't finalized yet or didn't succeed.
+pub async fn get_finalized_transaction(
+    signature: &Signature,
+) -> Result<VersionedTransaction, String> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentLevel::Finalized),
+        max_supported_transaction_version: Some(0),
     };
+    let tx = rpc_client()
+        .get_transaction(signature, Some(config))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Transaction {signature} is not finalized"))?
+        .transaction;
 
-    let to = Pubkey::from_str(to.as_str()).expect("Invalid pubkey");
+    if let Some(err) = tx.meta.and_then(|meta| meta.err) {
+        return Err(format!("Transaction {signature} failed: {err}"));
+    }
+    tx.transaction
+        .decode()
+        .ok_or_else(|| format!("Failed to decode transaction {signature}"))
+}
 
-    let transfer_ix = transfer(&from, &to, amount);
+/// Transfers SOL from the treasury and waits for confirmation, returning the transaction
+/// signature.
+pub async fn transfer_sol_from_treasury(to: String, amount: u64) -> Result<String, String> {
+    let tx = sign_transfer_from_treasury(&to, amount).await?;
+    send_and_confirm(tx).await
+}
 
-    let mut tx = Transaction::new_unsigned(Message::new(&[transfer_ix], Some(&from)));
+/// Signs a transfer from the treasury, using its durable nonce instead of a recent blockhash so
+/// the transaction can't expire while it is being threshold signed.
+///
+/// Every transaction signed with the same nonce is mutually exclusive: once one lands, the nonce
+/// advances and the others can't land anymore.
+pub async fn sign_transfer_from_treasury(to: &str, amount: u64) -> Result<Transaction, String> {
+    let signer = treasury_signer().await?;
+    let treasury = signer.pubkey();
+    let to = Pubkey::from_str(to).map_err(|e| e.to_string())?;
+
+    let client = rpc_client();
+
+    let nonce_pubkey = treasury_nonce_address(&treasury);
+    let nonce = client
+        .get_nonce_account(&nonce_pubkey)
+        .await
+        .map_err(|e| format!("Failed to fetch treasury nonce {nonce_pubkey}: {e}"))?;
 
-    let latest_blockhash = client
-        .get_latest_blockhash(Some(context_config))
+    let mut message = Message::new_with_nonce(
+        vec![transfer(&treasury, &to, amount)],
+        Some(&treasury),
+        &nonce_pubkey,
+        &nonce,
+    );
+    if let Err(err) = client
+        .add_compute_budget(
+            &mut message,
+            Some(TRANSFER_COMPUTE_UNIT_LIMIT + ADVANCE_NONCE_COMPUTE_UNITS),
+            PRIORITY_FEE_PERCENTILE,
+        )
         .await
-        .unwrap()
-        .parse_value()
-        .blockhash;
+    {
+        ic_cdk::api::print(format!("Failed to estimate priority fee: {err}"));
+    }
 
-    tx.set_latest_blockhash(&BlockHash::from_str(latest_blockhash.as_str()).unwrap());
+    let mut tx = Transaction::new_unsigned(message);
+    tx.try_sign(&[&signer], nonce.blockhash())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(tx)
+}
 
-    let secret_key: [u8; 32] = keypair[0..32].try_into().unwrap();
-    tx.sign(0, &secret_key);
+/// Sends a transaction signed with a durable nonce and waits for confirmation, returning its
+/// signature.
+pub async fn send_and_confirm(tx: Transaction) -> Result<String, String> {
+    let client = rpc_client();
 
-    let signature = client.send_transaction(tx, rpc_transaction_config).await;
+    // The nonce doesn't expire, but confirmation still needs a deadline: the transaction is
+    // re-broadcast until a blockhash fetched after signing expires.
+    let (_, last_valid_block_height) = latest_blockhash(&client).await?;
 
-    ic_cdk::api::print(format!("{:?}", signature));
+    let config = RpcSendAndConfirmConfig {
+        send_config: send_transaction_config(),
+        ..Default::default()
+    };
+    let confirmation = client
+        .send_and_confirm_transaction(tx, last_valid_block_height, config)
+        .await;
 
-    match signature {
-        Ok(sig) => sig.to_string(),
-        Err(err) => err.to_string(),
-    }
+    ic_cdk::api::print(format!("{:?}", confirmation));
+    confirmation_result(confirmation)
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub struct InitializeInstruction {
-    discriminator: [u8; 8],
+/// Outcome of a treasury transfer signed with a durable nonce.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TreasuryTransferStatus {
+    Confirmed,
+    /// The transaction landed but failed, advancing the nonce.
+    Failed(String),
+    /// The transaction hasn't landed yet, but still can.
+    Pending,
+    /// The nonce advanced without the transaction, which can't land anymore.
+    Dropped,
 }
 
-pub async fn call_anchor(from: String, keypair: [u8; 64]) -> String {
-    let cluster = Cluster::Localnet;
-    let rpc = RpcApi::new(cluster.url());
-    ic_cdk::api::print(cluster.url());
+/// Checks whether a treasury transfer signed with a durable nonce landed.
+pub async fn treasury_transfer_status(tx: &Transaction) -> Result<TreasuryTransferStatus, String> {
+    let signature = tx.signatures[0];
+    let client = rpc_client();
+
+    // Read the nonce before the signature: a transaction landing in between would otherwise be
+    // reported as dropped.
+    let treasury = tx.message.account_keys[0];
+    let nonce_pubkey = treasury_nonce_address(&treasury);
+    let nonce = client
+        .get_nonce_account(&nonce_pubkey)
+        .await
+        .map_err(|e| format!("Failed to fetch treasury nonce {nonce_pubkey}: {e}"))?;
 
-    let config = RpcClientConfig {
-        response_consensus: Some(ConsensusStrategy::Equality),
-        response_size_estimate: None,
-        request_cost_calculator: None,
-        host_validator: None,
-        transform_context: None,
-        use_compression: false,
-        is_demo_active: true,
+    // Without the history search, RPC nodes only know the signatures of recent blocks, and a
+    // transfer that landed earlier would look dropped and be sent again.
+    let config = RpcSignatureStatusConfig {
+        search_transaction_history: true,
     };
+    let status = client
+        .get_signature_statuses(&[signature], Some(config))
+        .await
+        .map_err(|e| e.to_string())?
+        .parse_value()
+        .pop()
+        .flatten();
 
-    let context_config = RpcContextConfig {
-        commitment: Some(CommitmentLevel::Finalized),
-        min_context_slot: None,
+    if let Some(status) = status {
+        if let Some(err) = status.err {
+            return Ok(TreasuryTransferStatus::Failed(err.to_string()));
+        }
+        if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+            return Ok(TreasuryTransferStatus::Confirmed);
+        }
+        return Ok(TreasuryTransferStatus::Pending);
+    }
+
+    if nonce.blockhash() == tx.message.recent_blockhash {
+        Ok(TreasuryTransferStatus::Pending)
+    } else {
+        Ok(TreasuryTransferStatus::Dropped)
+    }
+}
+
+/// Re-broadcasts a signed transaction, returning its signature.
+pub async fn rebroadcast(tx: Transaction) -> Result<String, String> {
+    let config = RpcSendTransactionConfig {
+        skip_preflight: true,
+        ..send_transaction_config()
     };
+    rpc_client()
+        .send_transaction(tx, config)
+        .await
+        .map(|signature| signature.to_string())
+        .map_err(|e| e.to_string())
+}
 
-    let client = RpcClient::new(vec![rpc], Some(config));
+/// Creates the treasury's durable nonce account, funded by and under the authority of the
+/// treasury.
+pub async fn create_treasury_nonce_account() -> Result<String, String> {
+    let signer = treasury_signer().await?;
+    let treasury = signer.pubkey();
+    let nonce_pubkey = treasury_nonce_address(&treasury);
 
-    let rpc_transaction_config = RpcSendTransactionConfig {
-        encoding: Some(ic_solana::types::UiTransactionEncoding::Base64),
-        skip_preflight: false,
-        preflight_commitment: Some(CommitmentLevel::Finalized),
-        max_retries: None,
-        min_context_slot: None,
+    let client = rpc_client();
+
+    let lamports = client
+        .get_minimum_balance_for_rent_exemption(NONCE_ACCOUNT_LENGTH, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let instructions = create_nonce_account_with_seed(
+        &treasury,
+        &nonce_pubkey,
+        &treasury,
+        TREASURY_NONCE_SEED,
+        &treasury,
+        lamports,
+    );
+    let mut tx = Transaction::new_unsigned(Message::new(&instructions, Some(&treasury)));
+    let (blockhash, last_valid_block_height) = latest_blockhash(&client).await?;
+
+    tx.try_sign(&[&signer], blockhash)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let config = RpcSendAndConfirmConfig {
+        send_config: send_transaction_config(),
+        ..Default::default()
     };
+    let confirmation = client
+        .send_and_confirm_transaction(tx, last_valid_block_height, config)
+        .await;
 
-    let from = Pubkey::from_str(from.as_str()).expect("Invalid pubkey");
+    confirmation_result(confirmation)?;
+    Ok(nonce_pubkey.to_string())
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct InitializeInstruction {
+    discriminator: [u8; 8],
+}
 
+/// Returns the instruction initializing the Anchor program for `from`.
+pub fn anchor_initialize_instruction(from: &Pubkey) -> Instruction {
     // Simplified accounts vector to match IDL
     let accounts = vec![
-        AccountMeta::new(from, true), // writable = true, signer = true
+        AccountMeta::new(*from, true), // writable = true, signer = true
     ];
 
     // Use the discriminator directly as instruction data
-    let ix = Instruction {
+    Instruction {
         program_id: Pubkey::from_str("FKSxwAshj48hxAWxGRSK9sLp3XR5wiyeawa6brtp9EjH")
             .expect("Invalid program id"),
         accounts,
         data: INITIALIZE_DISCRIMINATOR.to_vec(),
-    };
+    }
+}
 
-    let message = Message::new(&[ix], Some(&from));
-    let mut tx = Transaction::new_unsigned(message);
+/// Builds a transaction paid by `payer` with a fresh blockhash, for the wallet to sign. Returns
+/// the base64 encoded unsigned transaction.
+pub async fn build_unsigned_transaction(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+) -> Result<String, String> {
+    let (blockhash, _) = latest_blockhash(&rpc_client()).await?;
+    let message = Message::new_with_blockhash(instructions, Some(payer), &blockhash);
+    Ok(BASE64.encode(Transaction::new_unsigned(message).serialize()))
+}
 
-    let latest_blockhash = client
-        .get_latest_blockhash(Some(context_config))
+/// Decodes a base64 encoded transaction signed by a wallet, checking its signatures.
+pub fn decode_signed_transaction(tx: &str) -> Result<Transaction, String> {
+    let bytes = BASE64
+        .decode(tx)
+        .map_err(|e| format!("Invalid base64 transaction: {e}"))?;
+    let tx: Transaction =
+        bincode::deserialize(&bytes).map_err(|e| format!("Invalid transaction: {e}"))?;
+    if !tx.verify_signatures() {
+        return Err("Transaction signatures are invalid".to_string());
+    }
+    Ok(tx)
+}
+
+/// Submits a signed transaction, after checking that it carries exactly `instructions`, paid by
+/// `payer`. Returns its signature.
+pub async fn submit_signed_transaction(
+    tx: Transaction,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+) -> Result<String, String> {
+    let expected =
+        Message::new_with_blockhash(instructions, Some(payer), &tx.message.recent_blockhash);
+    if tx.message != expected {
+        return Err("Transaction does not match the expected instructions".to_string());
+    }
+
+    let signature = rpc_client()
+        .send_transaction(tx, send_transaction_config())
         .await
-        .unwrap()
-        .parse_value()
-        .blockhash;
+        .map_err(|e| e.to_string())?;
+    ic_cdk::api::print(format!("Transaction submitted: {}", signature));
+    Ok(signature.to_string())
+}
 
-    tx.set_latest_blockhash(&BlockHash::from_str(latest_blockhash.as_str()).unwrap());
+#[cfg(test)]
+mod tests {
+    use ic_solana::signer::Keypair;
 
-    let secret_key: [u8; 32] = keypair[0..32].try_into().unwrap();
-    tx.sign(0, &secret_key);
+    use super::*;
 
-    // Add detailed logging
-    let signature = client.send_transaction(tx, rpc_transaction_config).await;
+    const SECRET_KEY: [u8; 32] = [1; 32];
 
-    match signature {
-        Ok(s) => {
-            ic_cdk::api::print(format!("Transaction successful: {}", s));
-            s.to_string()
-        }
-        Err(e) => {
-            ic_cdk::api::print(format!("Transaction failed: {:?}", e));
-            e.to_string()
-        }
+    fn signed_transfer(lamports: u64) -> (Transaction, Vec<Instruction>, Pubkey) {
+        let payer = Keypair::from_secret_key(&SECRET_KEY).unwrap().pubkey();
+        let instructions = vec![transfer(&payer, &Pubkey::from([2; 32]), lamports)];
+        let message =
+            Message::new_with_blockhash(&instructions, Some(&payer), &BlockHash::default());
+        let mut tx = Transaction::new_unsigned(message);
+        tx.sign(0, &SECRET_KEY);
+        (tx, instructions, payer)
+    }
+
+    #[test]
+    fn test_decode_signed_transaction() {
+        let (tx, _, _) = signed_transfer(1_000);
+        let decoded = decode_signed_transaction(&BASE64.encode(tx.serialize())).unwrap();
+        assert_eq!(decoded, tx);
+
+        let mut forged = tx.clone();
+        forged.signatures[0] = Signature::default();
+        assert!(decode_signed_transaction(&BASE64.encode(forged.serialize())).is_err());
+
+        let mut tampered = tx.clone();
+        tampered.message.recent_blockhash = BlockHash([3; 32]);
+        assert!(decode_signed_transaction(&BASE64.encode(tampered.serialize())).is_err());
+
+        assert!(decode_signed_transaction("not base64").is_err());
+        assert!(decode_signed_transaction(&BASE64.encode(b"not a transaction")).is_err());
+    }
+
+    #[test]
+    fn test_submit_signed_transaction_rejects_other_instructions() {
+        let (tx, instructions, payer) = signed_transfer(1_000);
+        let (_, other_instructions, _) = signed_transfer(2_000);
+
+        let result = futures::executor::block_on(submit_signed_transaction(
+            tx.clone(),
+            &other_instructions,
+            &payer,
+        ));
+        assert_eq!(
+            result,
+            Err("Transaction does not match the expected instructions".to_string())
+        );
+
+        let result = futures::executor::block_on(submit_signed_transaction(
+            tx,
+            &instructions,
+            &Pubkey::from([2; 32]),
+        ));
+        assert_eq!(
+            result,
+            Err("Transaction does not match the expected instructions".to_string())
+        );
     }
 }
@@ -0,0 +1,121 @@
+//! Stable memory layout of the canister.
+//!
+//! Everything stored here survives upgrades. Each structure gets its own virtual memory, so new
+//! ones must be given a new, never reused, [`MemoryId`].
+
+use std::cell::RefCell;
+
+use ic_stable_structures::{
+    memory_manager::{MemoryId, MemoryManager, VirtualMemory},
+    storable::Blob,
+    DefaultMemoryImpl, StableBTreeMap, StableCell,
+};
+
+use crate::{
+    auth::Role,
+    orders::Order,
+    payout_accounts::PayoutAccount,
+    quotes::{Quote, QuoteConfig},
+};
+
+pub type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// A principal, in the bounded form used in stable map keys.
+pub type PrincipalKey = Blob<29>;
+
+const ORDERS_MEMORY_ID: MemoryId = MemoryId::new(0);
+const ORDERS_BY_OWNER_MEMORY_ID: MemoryId = MemoryId::new(1);
+const NEXT_ORDER_ID_MEMORY_ID: MemoryId = MemoryId::new(2);
+const ACTIVE_ORDERS_MEMORY_ID: MemoryId = MemoryId::new(3);
+const ORDERS_BY_RAZORPAY_ID_MEMORY_ID: MemoryId = MemoryId::new(4);
+const WEBHOOK_EVENTS_MEMORY_ID: MemoryId = MemoryId::new(5);
+const PAYOUT_ACCOUNTS_MEMORY_ID: MemoryId = MemoryId::new(6);
+const DESTINATION_KEY_SECRET_MEMORY_ID: MemoryId = MemoryId::new(7);
+const QUOTE_CONFIG_MEMORY_ID: MemoryId = MemoryId::new(8);
+const QUOTES_MEMORY_ID: MemoryId = MemoryId::new(9);
+const NEXT_QUOTE_ID_MEMORY_ID: MemoryId = MemoryId::new(10);
+const ROLES_MEMORY_ID: MemoryId = MemoryId::new(11);
+
+thread_local! {
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
+
+    /// All orders, by id.
+    pub static ORDERS: RefCell<StableBTreeMap<u64, Order, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(ORDERS_MEMORY_ID))),
+    );
+
+    /// Index of the orders of each user.
+    pub static ORDERS_BY_OWNER: RefCell<StableBTreeMap<(PrincipalKey, u64), (), Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(ORDERS_BY_OWNER_MEMORY_ID)),
+        ));
+
+    /// Ids of the orders that haven't reached a final state.
+    pub static ACTIVE_ORDERS: RefCell<StableBTreeMap<u64, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(ACTIVE_ORDERS_MEMORY_ID))),
+    );
+
+    /// Orders by the id of their Razorpay order or payout.
+    pub static ORDERS_BY_RAZORPAY_ID: RefCell<StableBTreeMap<String, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(ORDERS_BY_RAZORPAY_ID_MEMORY_ID)),
+        ),
+    );
+
+    /// Ids of the Razorpay webhook events already handled, with the time they were received.
+    pub static WEBHOOK_EVENTS: RefCell<StableBTreeMap<String, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(WEBHOOK_EVENTS_MEMORY_ID))),
+    );
+
+    /// Razorpay contact and fund accounts of each user.
+    pub static PAYOUT_ACCOUNTS: RefCell<StableBTreeMap<PrincipalKey, PayoutAccount, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(PAYOUT_ACCOUNTS_MEMORY_ID)),
+        ));
+
+    /// Id of the next order.
+    pub static NEXT_ORDER_ID: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(NEXT_ORDER_ID_MEMORY_ID)), 0)
+            .expect("Failed to initialize the order id counter"),
+    );
+
+    /// Secret keying the card numbers of payout destinations, empty until first used, see
+    /// [`crate::payout_accounts`].
+    pub static DESTINATION_KEY_SECRET: RefCell<StableCell<Vec<u8>, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(DESTINATION_KEY_SECRET_MEMORY_ID)),
+            vec![],
+        )
+        .expect("Failed to initialize the destination key secret"),
+    );
+
+    /// Spread and fee applied to quotes.
+    pub static QUOTE_CONFIG: RefCell<StableCell<QuoteConfig, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(QUOTE_CONFIG_MEMORY_ID)),
+            QuoteConfig::default(),
+        )
+        .expect("Failed to initialize the quote config"),
+    );
+
+    /// Quotes that weren't ordered yet, by id.
+    pub static QUOTES: RefCell<StableBTreeMap<u64, Quote, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(QUOTES_MEMORY_ID))),
+    );
+
+    /// Id of the next quote.
+    pub static NEXT_QUOTE_ID: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(NEXT_QUOTE_ID_MEMORY_ID)), 0)
+            .expect("Failed to initialize the quote id counter"),
+    );
+
+    /// Roles granted to principals, see [`crate::auth`].
+    pub static ROLES: RefCell<StableBTreeMap<PrincipalKey, Role, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(ROLES_MEMORY_ID))),
+    );
+}
+
+pub fn principal_key(principal: &candid::Principal) -> PrincipalKey {
+    PrincipalKey::try_from(principal.as_slice()).expect("principals are at most 29 bytes")
+}
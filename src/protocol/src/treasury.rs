@@ -0,0 +1,65 @@
+use std::{cell::RefCell, str::FromStr};
+
+use ic_solana::{
+    signer::{SchnorrKey, Signer, ThresholdSigner},
+    system_instruction,
+    types::Pubkey,
+};
+
+/// Seed of the treasury's durable nonce account, derived from the treasury address.
+pub const TREASURY_NONCE_SEED: &str = "nonce";
+
+thread_local! {
+    static TREASURY_PUBKEY: RefCell<Option<Pubkey>> = const { RefCell::new(None) };
+}
+
+/// What a threshold key is used for. Each purpose gets its own derivation path, and therefore
+/// its own Solana address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyPurpose {
+    Treasury,
+}
+
+impl KeyPurpose {
+    pub fn derivation_path(&self) -> Vec<Vec<u8>> {
+        match self {
+            KeyPurpose::Treasury => vec![b"simpl".to_vec(), b"treasury".to_vec()],
+        }
+    }
+}
+
+/// The threshold key used by this canister, configured at build time via `SCHNORR_KEY_NAME`.
+pub fn schnorr_key() -> SchnorrKey {
+    option_env!("SCHNORR_KEY_NAME")
+        .map(|name| SchnorrKey::from_str(name).expect("Invalid SCHNORR_KEY_NAME"))
+        .unwrap_or_default()
+}
+
+/// Returns a signer for the treasury account, fetching its public key from the management
+/// canister on first use.
+pub async fn treasury_signer() -> Result<ThresholdSigner, String> {
+    let key = schnorr_key();
+    let derivation_path = KeyPurpose::Treasury.derivation_path();
+
+    if let Some(pubkey) = TREASURY_PUBKEY.with(|pubkey| *pubkey.borrow()) {
+        return Ok(ThresholdSigner::with_pubkey(key, derivation_path, pubkey));
+    }
+
+    let signer = ThresholdSigner::new(key, derivation_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    TREASURY_PUBKEY.with(|pubkey| *pubkey.borrow_mut() = Some(signer.pubkey()));
+    Ok(signer)
+}
+
+/// Returns the Solana address of the treasury.
+pub async fn treasury_address() -> Result<Pubkey, String> {
+    Ok(treasury_signer().await?.pubkey())
+}
+
+/// Returns the address of the durable nonce account used by treasury transactions, which stay
+/// valid however long threshold signing takes.
+pub fn treasury_nonce_address(treasury: &Pubkey) -> Pubkey {
+    Pubkey::create_with_seed(treasury, TREASURY_NONCE_SEED, &system_instruction::ID)
+        .expect("nonce seed is valid")
+}
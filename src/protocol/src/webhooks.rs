@@ -0,0 +1,202 @@
+//! Razorpay webhook receiver.
+//!
+//! Razorpay posts webhooks to `/webhooks/razorpay` on the canister's HTTP interface. The query
+//! `http_request` can't change state, so it asks the HTTP gateway to upgrade the call to
+//! `http_request_update`, which verifies the signature of the body and advances the matching
+//! order:
+//!
+//! - `payment.captured` marks an on-ramp order paid, then starts sending its SOL.
+//! - `payout.processed` settles an off-ramp order.
+//! - `payout.reversed` and `payout.failed` start refunding the SOL of an off-ramp order.
+//!
+//! Razorpay delivers webhooks at least once, so events are deduplicated by id. It stops
+//! redelivering them after a day, so their ids are forgotten after [`EVENT_RETENTION`].
+
+use candid::CandidType;
+use razorpay::{WebhookEvent, EVENT_ID_HEADER, SIGNATURE_HEADER};
+use serde::Deserialize;
+use serde_bytes::ByteBuf;
+
+use crate::{
+    build_setting, offramp, onramp,
+    orders::{self, OrderKind, OrderState},
+    processing,
+    state::WEBHOOK_EVENTS,
+};
+
+pub const WEBHOOK_PATH: &str = "/webhooks/razorpay";
+
+/// How long the ids of handled events are kept, in nanoseconds.
+pub const EVENT_RETENTION: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+    /// Asks the HTTP gateway to call `http_request_update` instead.
+    pub upgrade: Option<bool>,
+}
+
+impl HttpResponse {
+    fn new(status_code: u16, body: &str) -> Self {
+        Self {
+            status_code,
+            headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+            body: ByteBuf::from(body.as_bytes()),
+            upgrade: None,
+        }
+    }
+
+    fn upgrade() -> Self {
+        Self {
+            upgrade: Some(true),
+            ..Self::new(200, "")
+        }
+    }
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_webhook(&self) -> bool {
+        self.method.eq_ignore_ascii_case("POST") && self.url.split('?').next() == Some(WEBHOOK_PATH)
+    }
+}
+
+/// Handles a query call on the HTTP interface, upgrading webhooks to update calls.
+pub fn http_request(request: HttpRequest) -> HttpResponse {
+    if request.is_webhook() {
+        HttpResponse::upgrade()
+    } else {
+        HttpResponse::new(404, "Not found")
+    }
+}
+
+/// Handles an update call on the HTTP interface.
+pub fn http_request_update(request: HttpRequest) -> HttpResponse {
+    if !request.is_webhook() {
+        return HttpResponse::new(404, "Not found");
+    }
+    let webhook_secret = match build_setting(
+        "RAZORPAY_WEBHOOK_SECRET",
+        option_env!("RAZORPAY_WEBHOOK_SECRET"),
+    ) {
+        Ok(secret) => secret,
+        Err(e) => return HttpResponse::new(500, &e),
+    };
+
+    let signature = request.header(SIGNATURE_HEADER).unwrap_or_default();
+    if !razorpay::verify_webhook_signature(&request.body, signature, webhook_secret) {
+        return HttpResponse::new(400, "Invalid signature");
+    }
+    let Some(event_id) = request.header(EVENT_ID_HEADER).map(str::to_string) else {
+        return HttpResponse::new(400, "Missing event id");
+    };
+    let event: WebhookEvent = match serde_json::from_slice(&request.body) {
+        Ok(event) => event,
+        Err(e) => return HttpResponse::new(400, &format!("Invalid event: {e}")),
+    };
+
+    let now = ic_cdk::api::time();
+    forget_events_before(now.saturating_sub(EVENT_RETENTION));
+    let seen =
+        WEBHOOK_EVENTS.with(|events| events.borrow_mut().insert(event_id.clone(), now).is_some());
+    if seen {
+        return HttpResponse::new(200, "Already handled");
+    }
+
+    match handle_event(&event) {
+        Ok(()) => HttpResponse::new(200, "OK"),
+        Err(e) => {
+            // Forgotten, so the event is handled when Razorpay redelivers it.
+            WEBHOOK_EVENTS.with(|events| events.borrow_mut().remove(&event_id));
+            ic_cdk::api::print(format!("Failed to handle webhook {event_id}: {e}"));
+            HttpResponse::new(500, &e)
+        }
+    }
+}
+
+/// Forgets the events received before `time`.
+fn forget_events_before(time: u64) {
+    WEBHOOK_EVENTS.with(|events| {
+        let mut events = events.borrow_mut();
+        let expired: Vec<String> = events
+            .iter()
+            .filter(|(_, received)| *received < time)
+            .map(|(id, _)| id)
+            .collect();
+        for id in expired {
+            events.remove(&id);
+        }
+    });
+}
+
+fn handle_event(event: &WebhookEvent) -> Result<(), String> {
+    match event.event.as_str() {
+        "payment.captured" => {
+            let payment = event.payment().ok_or("Event has no payment")?;
+            let Some(order) = payment
+                .order_id
+                .as_deref()
+                .and_then(orders::find_by_razorpay_id)
+                .filter(|order| order.kind == OrderKind::OnRamp)
+            else {
+                return Ok(());
+            };
+            // Payments of the wrong amount are refunded when the order is next polled.
+            if order.state != OrderState::Created || !onramp::pays_for(&order, payment) {
+                return Ok(());
+            }
+            orders::update(order.id, |order| {
+                order.razorpay_payment_id = Some(payment.id.clone());
+                order.state = OrderState::Paid;
+            })?;
+            ic_cdk::spawn(processing::process_orders());
+            Ok(())
+        }
+        "payout.processed" | "payout.reversed" | "payout.failed" => {
+            let payout = event.payout().ok_or("Event has no payout")?;
+            let Some(order) = orders::find_by_razorpay_id(&payout.id)
+                .filter(|order| order.kind == OrderKind::OffRamp)
+            else {
+                return Ok(());
+            };
+            offramp::record_payout_status(order.id, payout)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forget_events_before() {
+        WEBHOOK_EVENTS.with(|events| {
+            let mut events = events.borrow_mut();
+            events.insert("evt_old".to_string(), 1_000);
+            events.insert("evt_new".to_string(), 1_000 + EVENT_RETENTION);
+        });
+
+        forget_events_before(1_001);
+
+        let ids: Vec<String> =
+            WEBHOOK_EVENTS.with(|events| events.borrow().iter().map(|(id, _)| id).collect());
+        assert_eq!(ids, vec!["evt_new".to_string()]);
+    }
+}
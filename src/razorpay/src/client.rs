@@ -0,0 +1,332 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ic_cdk::api::management_canister::http_request::{
+    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
+    TransformContext,
+};
+use ic_solana::outcalls::http_request_cycles;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{RazorpayError, RAZORPAY_BASE_URL};
+
+/// Response size limit of calls returning a single entity.
+pub const MAX_ENTITY_RESPONSE_BYTES: u64 = 16 * 1024;
+
+/// Response size limit of calls returning a collection.
+pub const MAX_COLLECTION_RESPONSE_BYTES: u64 = 256 * 1024;
+
+/// Response fields that may differ between the replicas fetching the same entity, dropped by
+/// [`transform_response`].
+const VOLATILE_FIELDS: &[&str] = &["status_details", "status_details_id", "updated_at"];
+
+/// Builds the query string of a URL from the parameters that are set.
+pub(crate) fn query_string<'a>(
+    params: impl IntoIterator<Item = (&'a str, Option<String>)>,
+) -> String {
+    params
+        .into_iter()
+        .filter_map(|(name, value)| Some(format!("{name}={}", percent_encode(&value?))))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Reduces a Razorpay response to what all replicas agree on: its status, and its body without
+/// the headers and [`VOLATILE_FIELDS`]. The canister exports it as a query, whose name is
+/// passed to [`RazorpayClient::with_transform`].
+pub fn transform_response(args: TransformArgs) -> HttpResponse {
+    let mut response = args.response;
+    response.headers.clear();
+    if let Ok(mut body) = serde_json::from_slice::<Value>(&response.body) {
+        strip_volatile_fields(&mut body);
+        response.body = serde_json::to_vec(&body).expect("JSON values serialize");
+    }
+    response
+}
+
+fn strip_volatile_fields(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            fields.retain(|name, _| !VOLATILE_FIELDS.contains(&name.as_str()));
+            fields.values_mut().for_each(strip_volatile_fields);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip_volatile_fields),
+        _ => {}
+    }
+}
+
+/// A page of entities returned by the Razorpay API.
+#[derive(Deserialize)]
+pub(crate) struct Collection<T> {
+    pub items: Vec<T>,
+}
+
+/// Client of the Razorpay and RazorpayX APIs, authenticated with an API key.
+#[derive(Clone, Debug)]
+pub struct RazorpayClient {
+    key_id: String,
+    key_secret: String,
+    base_url: String,
+    /// Query transforming responses with [`transform_response`].
+    transform_method: Option<String>,
+}
+
+impl RazorpayClient {
+    pub fn new(key_id: impl Into<String>, key_secret: impl Into<String>) -> Self {
+        Self {
+            key_id: key_id.into(),
+            key_secret: key_secret.into(),
+            base_url: RAZORPAY_BASE_URL.to_string(),
+            transform_method: None,
+        }
+    }
+
+    /// Sends requests to `base_url` instead of the Razorpay API, which must end with a `/`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Transforms responses with the canister query `method`, which must call
+    /// [`transform_response`]. Replicas only agree on responses once transformed.
+    pub fn with_transform(mut self, method: impl Into<String>) -> Self {
+        self.transform_method = Some(method.into());
+        self
+    }
+
+    /// Checks the signature Razorpay Checkout returns for a payment, keyed by the API key secret.
+    pub fn verify_payment_signature(
+        &self,
+        order_id: &str,
+        payment_id: &str,
+        signature: &str,
+    ) -> bool {
+        crate::verify_payment_signature(order_id, payment_id, signature, &self.key_secret)
+    }
+
+    pub(crate) async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        max_response_bytes: u64,
+    ) -> Result<T, RazorpayError> {
+        send(self.request(HttpMethod::GET, path, None, None, max_response_bytes)).await
+    }
+
+    pub(crate) async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+        max_response_bytes: u64,
+    ) -> Result<T, RazorpayError> {
+        let body = serde_json::to_vec(body).expect("request bodies serialize to JSON");
+        send(self.request(HttpMethod::POST, path, Some(body), None, max_response_bytes)).await
+    }
+
+    /// Sends a POST request that Razorpay handles once per `idempotency_key`, passed in the
+    /// `idempotency_header`. Every replica sends the request, and retries may send it again:
+    /// all of them get the response to the first one.
+    pub(crate) async fn post_idempotent<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+        idempotency_header: &str,
+        idempotency_key: &str,
+        max_response_bytes: u64,
+    ) -> Result<T, RazorpayError> {
+        send(self.idempotent_post_request(
+            path,
+            body,
+            idempotency_header,
+            idempotency_key,
+            max_response_bytes,
+        ))
+        .await
+    }
+
+    pub(crate) fn idempotent_post_request<B: Serialize>(
+        &self,
+        path: &str,
+        body: &B,
+        idempotency_header: &str,
+        idempotency_key: &str,
+        max_response_bytes: u64,
+    ) -> CanisterHttpRequestArgument {
+        let body = serde_json::to_vec(body).expect("request bodies serialize to JSON");
+        let header = HttpHeader {
+            name: idempotency_header.to_string(),
+            value: idempotency_key.to_string(),
+        };
+        self.request(
+            HttpMethod::POST,
+            path,
+            Some(body),
+            Some(header),
+            max_response_bytes,
+        )
+    }
+
+    /// Sends a PATCH request. HTTP outcalls only support GET, POST and HEAD, so it is sent as a
+    /// POST with an `X-HTTP-Method-Override` header.
+    pub(crate) async fn patch<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+        max_response_bytes: u64,
+    ) -> Result<T, RazorpayError> {
+        send(self.patch_request(path, body, max_response_bytes)).await
+    }
+
+    fn patch_request<B: Serialize>(
+        &self,
+        path: &str,
+        body: &B,
+        max_response_bytes: u64,
+    ) -> CanisterHttpRequestArgument {
+        let body = serde_json::to_vec(body).expect("request bodies serialize to JSON");
+        let header = HttpHeader {
+            name: "X-HTTP-Method-Override".to_string(),
+            value: "PATCH".to_string(),
+        };
+        self.request(
+            HttpMethod::POST,
+            path,
+            Some(body),
+            Some(header),
+            max_response_bytes,
+        )
+    }
+
+    fn request(
+        &self,
+        method: HttpMethod,
+        path: &str,
+        body: Option<Vec<u8>>,
+        extra_header: Option<HttpHeader>,
+        max_response_bytes: u64,
+    ) -> CanisterHttpRequestArgument {
+        let auth = BASE64.encode(format!("{}:{}", self.key_id, self.key_secret));
+        let mut headers = vec![HttpHeader {
+            name: "Authorization".to_string(),
+            value: format!("Basic {}", auth),
+        }];
+        if body.is_some() {
+            headers.push(HttpHeader {
+                name: "Content-Type".to_string(),
+                value: "application/json".to_string(),
+            });
+        }
+        headers.extend(extra_header);
+
+        CanisterHttpRequestArgument {
+            url: format!("{}{}", self.base_url, path),
+            max_response_bytes: Some(max_response_bytes),
+            method,
+            headers,
+            body,
+            transform: self
+                .transform_method
+                .clone()
+                .map(|method| TransformContext::from_name(method, vec![])),
+        }
+    }
+}
+
+pub(crate) async fn send<T: DeserializeOwned>(
+    request: CanisterHttpRequestArgument,
+) -> Result<T, RazorpayError> {
+    let request_bytes = request.url.len()
+        + request
+            .headers
+            .iter()
+            .map(|header| header.name.len() + header.value.len())
+            .sum::<usize>()
+        + request.body.as_ref().map_or(0, Vec::len);
+    let cycles = http_request_cycles(
+        request_bytes as u64,
+        request.max_response_bytes.unwrap_or_default(),
+    );
+
+    let (response,) = http_request(request, cycles)
+        .await
+        .map_err(|(code, message)| RazorpayError::Outcall(format!("{code:?}: {message}")))?;
+
+    let status = u16::try_from(&response.status.0).unwrap_or(u16::MAX);
+    if !(200..300).contains(&status) {
+        return Err(RazorpayError::from_response(status, &response.body));
+    }
+    serde_json::from_slice(&response.body).map_err(|e| RazorpayError::InvalidResponse {
+        status,
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_string() {
+        assert_eq!(query_string([("count", None)]), "");
+        assert_eq!(
+            query_string([
+                ("email", Some("user+1@example.com".to_string())),
+                ("active", None),
+                ("count", Some("10".to_string())),
+            ]),
+            "email=user%2B1%40example.com&count=10"
+        );
+    }
+
+    #[test]
+    fn test_transform_response() {
+        let response = HttpResponse {
+            status: 200u16.into(),
+            headers: vec![HttpHeader {
+                name: "Date".to_string(),
+                value: "Mon, 13 Nov 2023 22:13:20 GMT".to_string(),
+            }],
+            body: br#"{"id": "pout_1", "status": "processing", "updated_at": 1700000000,
+                "status_details": {"reason": "payout_bank_processing"}}"#
+                .to_vec(),
+        };
+        let args = TransformArgs {
+            response,
+            context: vec![],
+        };
+
+        let transformed = transform_response(args);
+        assert_eq!(transformed.status, 200u16);
+        assert!(transformed.headers.is_empty());
+        assert_eq!(
+            serde_json::from_slice::<Value>(&transformed.body).unwrap(),
+            serde_json::json!({"id": "pout_1", "status": "processing"})
+        );
+    }
+
+    #[test]
+    fn test_patch_request() {
+        let client = RazorpayClient::new("rzp_test_key", "secret");
+        let request = client.patch_request(
+            "contacts/cont_1",
+            &serde_json::json!({ "active": false }),
+            MAX_ENTITY_RESPONSE_BYTES,
+        );
+
+        assert_eq!(request.method, HttpMethod::POST);
+        let header = &request.headers[request.headers.len() - 1];
+        assert_eq!(header.name, "X-HTTP-Method-Override");
+        assert_eq!(header.value, "PATCH");
+        assert_eq!(request.body.unwrap(), br#"{"active":false}"#);
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::query_string, Collection, RazorpayClient, RazorpayError, MAX_COLLECTION_RESPONSE_BYTES,
+    MAX_ENTITY_RESPONSE_BYTES,
+};
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CreateContactRequest {
+    pub name: String,
+    pub email: String,
+    pub contact: String,
+    /// Identifier of the contact on our side.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_id: Option<String>,
+}
+
+/// A beneficiary of payouts.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Contact {
+    pub id: String,
+    pub name: String,
+    pub email: Option<String>,
+    /// Phone number.
+    pub contact: Option<String>,
+    pub reference_id: Option<String>,
+    pub active: bool,
+}
+
+/// Filters of [`RazorpayClient::list_contacts`]. Unset fields don't filter.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContactFilter {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub contact: Option<String>,
+    pub reference_id: Option<String>,
+    pub active: Option<bool>,
+    /// Number of contacts to return, at most 100.
+    pub count: Option<u32>,
+    /// Number of contacts to skip.
+    pub skip: Option<u32>,
+}
+
+impl ContactFilter {
+    fn query(&self) -> String {
+        query_string([
+            ("name", self.name.clone()),
+            ("email", self.email.clone()),
+            ("contact", self.contact.clone()),
+            ("reference_id", self.reference_id.clone()),
+            (
+                "active",
+                self.active.map(|active| (active as u8).to_string()),
+            ),
+            ("count", self.count.map(|count| count.to_string())),
+            ("skip", self.skip.map(|skip| skip.to_string())),
+        ])
+    }
+}
+
+impl RazorpayClient {
+    pub async fn create_contact(
+        &self,
+        request: &CreateContactRequest,
+    ) -> Result<Contact, RazorpayError> {
+        self.post("contacts", request, MAX_ENTITY_RESPONSE_BYTES)
+            .await
+    }
+
+    pub async fn fetch_contact(&self, contact_id: &str) -> Result<Contact, RazorpayError> {
+        self.get(&format!("contacts/{contact_id}"), MAX_ENTITY_RESPONSE_BYTES)
+            .await
+    }
+
+    /// Returns the contacts of the account, newest first.
+    pub async fn list_contacts(
+        &self,
+        filter: &ContactFilter,
+    ) -> Result<Vec<Contact>, RazorpayError> {
+        let contacts: Collection<Contact> = self
+            .get(
+                &format!("contacts?{}", filter.query()),
+                MAX_COLLECTION_RESPONSE_BYTES,
+            )
+            .await?;
+        Ok(contacts.items)
+    }
+
+    /// Deactivates a contact, which can't receive payouts anymore.
+    pub async fn deactivate_contact(&self, contact_id: &str) -> Result<Contact, RazorpayError> {
+        self.patch(
+            &format!("contacts/{contact_id}"),
+            &serde_json::json!({ "active": false }),
+            MAX_ENTITY_RESPONSE_BYTES,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contact_filter_query() {
+        let filter = ContactFilter {
+            reference_id: Some("2vxsx-fae".to_string()),
+            active: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(filter.query(), "reference_id=2vxsx-fae&active=1");
+    }
+}
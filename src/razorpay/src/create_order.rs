@@ -1,47 +1,95 @@
-use ic_cdk::api::management_canister::http_request::{
-    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod,
+use ic_cdk::api::management_canister::http_request::CanisterHttpRequestArgument;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::{query_string, send},
+    Collection, RazorpayClient, RazorpayError, MAX_COLLECTION_RESPONSE_BYTES,
+    MAX_ENTITY_RESPONSE_BYTES,
 };
 
-use crate::RAZORPAY_BASE_URL;
-
-pub async fn create_order(amount: u64, encoded_auth: String) -> Result<String, String> {
-    let headers = vec![
-        HttpHeader {
-            name: "Content-Type".to_string(),
-            value: "application/json".to_string(),
-        },
-        HttpHeader {
-            name: "Authorization".to_string(),
-            value: format!("Basic {}", encoded_auth),
-        },
-    ];
-
-    let body = serde_json::json!({
-        "amount": amount,
-        "currency": "INR",
-    });
-
-    let body_bytes = serde_json::to_vec(&body).unwrap();
-
-    let request = CanisterHttpRequestArgument {
-        url: format!("{}orders", RAZORPAY_BASE_URL),
-        max_response_bytes: None,
-        method: HttpMethod::POST,
-        headers,
-        body: Some(body_bytes),
-        transform: None,
-    };
-
-    let response = http_request(request, 1_603_131_200).await;
-
-    match response {
-        Ok(response) => {
-            let response_body = String::from_utf8(response.0.body).unwrap();
-            let response_json: serde_json::Value = serde_json::from_str(&response_body).unwrap();
-            ic_cdk::println!("response_json: {:?}", response_json);
-            let id = response_json["id"].as_str().expect("Something went wrong");
-            Ok(id.to_string())
-        }
-        Err(err) => Err(format!("Error creating order: {}", err.1)),
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CreateOrderRequest {
+    /// Amount in paise.
+    pub amount: u64,
+    pub currency: String,
+    /// Identifier of the order on our side, at most 40 characters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<String>,
+}
+
+/// An order, which the user pays through Razorpay Checkout.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RazorpayOrder {
+    pub id: String,
+    /// Amount in paise.
+    pub amount: u64,
+    pub currency: String,
+    pub receipt: Option<String>,
+    /// One of `created`, `attempted` or `paid`.
+    pub status: String,
+}
+
+/// Header of the key Razorpay deduplicates order requests by.
+pub const ORDER_IDEMPOTENCY_HEADER: &str = "X-Order-Idempotency";
+
+impl RazorpayClient {
+    /// Creates an order of `amount` paise, once per `receipt`: requests with a receipt already
+    /// used return the order it created. An order whose creation seemed to fail can be found by
+    /// its receipt with [`Self::find_order_by_receipt`].
+    pub async fn create_order(
+        &self,
+        amount: u64,
+        receipt: &str,
+    ) -> Result<RazorpayOrder, RazorpayError> {
+        send(self.create_order_request(amount, receipt)).await
+    }
+
+    fn create_order_request(&self, amount: u64, receipt: &str) -> CanisterHttpRequestArgument {
+        let request = CreateOrderRequest {
+            amount,
+            currency: "INR".to_string(),
+            receipt: Some(receipt.to_string()),
+        };
+        self.idempotent_post_request(
+            "orders",
+            &request,
+            ORDER_IDEMPOTENCY_HEADER,
+            receipt,
+            MAX_ENTITY_RESPONSE_BYTES,
+        )
+    }
+
+    /// Returns the order created with `receipt`, if any.
+    pub async fn find_order_by_receipt(
+        &self,
+        receipt: &str,
+    ) -> Result<Option<RazorpayOrder>, RazorpayError> {
+        let query = query_string([("receipt", Some(receipt.to_string()))]);
+        let orders: Collection<RazorpayOrder> = self
+            .get(&format!("orders?{query}"), MAX_COLLECTION_RESPONSE_BYTES)
+            .await?;
+        Ok(orders.items.into_iter().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[test]
+    fn test_create_order_request() {
+        let client = RazorpayClient::new("rzp_test_key", "secret");
+        let request = client.create_order_request(50_000, "onramp-order-7");
+
+        assert!(request.headers.iter().any(|header| {
+            header.name == ORDER_IDEMPOTENCY_HEADER && header.value == "onramp-order-7"
+        }));
+        let body: Value = serde_json::from_slice(&request.body.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({"amount": 50_000, "currency": "INR", "receipt": "onramp-order-7"})
+        );
     }
 }
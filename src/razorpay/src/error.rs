@@ -0,0 +1,129 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+/// Error of a call to the Razorpay API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RazorpayError {
+    /// The HTTP outcall was rejected before Razorpay responded.
+    Outcall(String),
+    /// Razorpay rejected the request, see <https://razorpay.com/docs/errors/>.
+    Api {
+        /// HTTP status of the response.
+        status: u16,
+        /// Such as `BAD_REQUEST_ERROR`.
+        code: String,
+        description: String,
+        /// The request field at fault, if any.
+        field: Option<String>,
+    },
+    /// The response couldn't be parsed.
+    InvalidResponse { status: u16, message: String },
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetails,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetails {
+    code: String,
+    description: String,
+    field: Option<String>,
+}
+
+impl RazorpayError {
+    /// Parses the error body Razorpay returns with a non-2xx status.
+    pub fn from_response(status: u16, body: &[u8]) -> Self {
+        match serde_json::from_slice::<ErrorBody>(body) {
+            Ok(ErrorBody { error }) => Self::Api {
+                status,
+                code: error.code,
+                description: error.description,
+                field: error.field,
+            },
+            Err(_) => Self::InvalidResponse {
+                status,
+                message: String::from_utf8_lossy(body).into_owned(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for RazorpayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Outcall(message) => write!(f, "Razorpay request failed: {message}"),
+            Self::Api {
+                status,
+                code,
+                description,
+                field: Some(field),
+            } => write!(
+                f,
+                "Razorpay error {status} {code} on {field}: {description}"
+            ),
+            Self::Api {
+                status,
+                code,
+                description,
+                field: None,
+            } => write!(f, "Razorpay error {status} {code}: {description}"),
+            Self::InvalidResponse { status, message } => {
+                write!(f, "Invalid Razorpay response ({status}): {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RazorpayError {}
+
+impl From<RazorpayError> for String {
+    fn from(error: RazorpayError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_from_response() {
+        let body = br#"{
+            "error": {
+                "code": "BAD_REQUEST_ERROR",
+                "description": "The amount must be atleast INR 1.00",
+                "source": "business",
+                "step": "payment_initiation",
+                "reason": "input_validation_failed",
+                "metadata": {},
+                "field": "amount"
+            }
+        }"#;
+
+        let error = RazorpayError::from_response(400, body);
+        assert_eq!(
+            error,
+            RazorpayError::Api {
+                status: 400,
+                code: "BAD_REQUEST_ERROR".to_string(),
+                description: "The amount must be atleast INR 1.00".to_string(),
+                field: Some("amount".to_string()),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Razorpay error 400 BAD_REQUEST_ERROR on amount: The amount must be atleast INR 1.00"
+        );
+
+        assert_eq!(
+            RazorpayError::from_response(502, b"Bad Gateway"),
+            RazorpayError::InvalidResponse {
+                status: 502,
+                message: "Bad Gateway".to_string(),
+            }
+        );
+    }
+}
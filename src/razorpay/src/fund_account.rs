@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Collection, RazorpayClient, RazorpayError, MAX_COLLECTION_RESPONSE_BYTES,
+    MAX_ENTITY_RESPONSE_BYTES,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BankAccount {
+    pub name: String,
+    pub ifsc: String,
+    pub account_number: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Vpa {
+    pub address: String,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Card {
+    pub name: String,
+    pub number: String,
+}
+
+/// The card of a fund account, as returned by Razorpay.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CardDetails {
+    pub name: Option<String>,
+    pub last4: Option<String>,
+    pub network: Option<String>,
+}
+
+/// The account type of a fund account, with its details.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "account_type", rename_all = "snake_case")]
+pub enum FundAccountDetails {
+    BankAccount { bank_account: BankAccount },
+    Vpa { vpa: Vpa },
+    Card { card: Card },
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CreateFundAccountRequest {
+    pub contact_id: String,
+    #[serde(flatten)]
+    pub details: FundAccountDetails,
+}
+
+/// An account of a contact that payouts are made to.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FundAccount {
+    pub id: String,
+    pub contact_id: String,
+    /// One of `bank_account`, `vpa` or `card`, telling which details are set.
+    pub account_type: String,
+    pub bank_account: Option<BankAccount>,
+    pub vpa: Option<Vpa>,
+    pub card: Option<CardDetails>,
+    pub active: bool,
+}
+
+impl RazorpayClient {
+    pub async fn create_fund_account(
+        &self,
+        request: &CreateFundAccountRequest,
+    ) -> Result<FundAccount, RazorpayError> {
+        self.post("fund_accounts", request, MAX_ENTITY_RESPONSE_BYTES)
+            .await
+    }
+
+    pub async fn fetch_fund_account(
+        &self,
+        fund_account_id: &str,
+    ) -> Result<FundAccount, RazorpayError> {
+        self.get(
+            &format!("fund_accounts/{fund_account_id}"),
+            MAX_ENTITY_RESPONSE_BYTES,
+        )
+        .await
+    }
+
+    /// Returns the fund accounts of a contact, newest first.
+    pub async fn list_fund_accounts(
+        &self,
+        contact_id: &str,
+    ) -> Result<Vec<FundAccount>, RazorpayError> {
+        let fund_accounts: Collection<FundAccount> = self
+            .get(
+                &format!("fund_accounts?contact_id={contact_id}"),
+                MAX_COLLECTION_RESPONSE_BYTES,
+            )
+            .await?;
+        Ok(fund_accounts.items)
+    }
+
+    /// Deactivates a fund account, which can't receive payouts anymore.
+    pub async fn deactivate_fund_account(
+        &self,
+        fund_account_id: &str,
+    ) -> Result<FundAccount, RazorpayError> {
+        self.patch(
+            &format!("fund_accounts/{fund_account_id}"),
+            &serde_json::json!({ "active": false }),
+            MAX_ENTITY_RESPONSE_BYTES,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_create_fund_account_request() {
+        let request = CreateFundAccountRequest {
+            contact_id: "cont_00000000000001".to_string(),
+            details: FundAccountDetails::Vpa {
+                vpa: Vpa {
+                    address: "user@upi".to_string(),
+                },
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "contact_id": "cont_00000000000001",
+                "account_type": "vpa",
+                "vpa": { "address": "user@upi" },
+            })
+        );
+    }
+}
@@ -1,7 +1,21 @@
+pub mod client;
 pub mod constants;
+pub mod contact;
 pub mod create_order;
+pub mod error;
+pub mod fund_account;
+pub mod payment;
 pub mod payout;
+pub mod signature;
+pub mod webhook;
 
+pub use client::*;
 pub use constants::*;
+pub use contact::*;
 pub use create_order::*;
+pub use error::*;
+pub use fund_account::*;
+pub use payment::*;
 pub use payout::*;
+pub use signature::*;
+pub use webhook::*;
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Collection, RazorpayClient, RazorpayError, MAX_COLLECTION_RESPONSE_BYTES,
+    MAX_ENTITY_RESPONSE_BYTES,
+};
+
+/// A payment made against a Razorpay order.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Payment {
+    pub id: String,
+    /// Amount in paise.
+    pub amount: u64,
+    pub currency: String,
+    /// One of `created`, `authorized`, `captured`, `refunded` or `failed`.
+    pub status: String,
+    /// The order the payment was made against.
+    pub order_id: Option<String>,
+}
+
+impl Payment {
+    pub fn is_captured(&self) -> bool {
+        self.status == "captured"
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct RefundRequest {
+    /// Amount in paise.
+    pub amount: u64,
+}
+
+/// A refund of a captured payment.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Refund {
+    pub id: String,
+    pub payment_id: String,
+    /// Amount in paise.
+    pub amount: u64,
+    /// One of `pending`, `processed` or `failed`.
+    pub status: String,
+}
+
+/// Header of the key Razorpay deduplicates refund requests by.
+pub const REFUND_IDEMPOTENCY_HEADER: &str = "X-Refund-Idempotency";
+
+impl RazorpayClient {
+    /// Returns the payments made against an order.
+    pub async fn fetch_order_payments(
+        &self,
+        order_id: &str,
+    ) -> Result<Vec<Payment>, RazorpayError> {
+        let payments: Collection<Payment> = self
+            .get(
+                &format!("orders/{order_id}/payments"),
+                MAX_COLLECTION_RESPONSE_BYTES,
+            )
+            .await?;
+        Ok(payments.items)
+    }
+
+    /// Refunds `amount` paise of a captured payment, once per `idempotency_key`: requests with
+    /// a key already used return the refund it created.
+    pub async fn refund_payment(
+        &self,
+        payment_id: &str,
+        amount: u64,
+        idempotency_key: &str,
+    ) -> Result<Refund, RazorpayError> {
+        self.post_idempotent(
+            &format!("payments/{payment_id}/refund"),
+            &RefundRequest { amount },
+            REFUND_IDEMPOTENCY_HEADER,
+            idempotency_key,
+            MAX_ENTITY_RESPONSE_BYTES,
+        )
+        .await
+    }
+}
@@ -1,213 +1,408 @@
 use candid::CandidType;
-use ic_cdk::api::management_canister::http_request::{
-    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod,
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{
+    client::query_string, BankAccount, Card, Collection, FundAccountDetails, RazorpayClient,
+    RazorpayError, Vpa, MAX_COLLECTION_RESPONSE_BYTES, MAX_ENTITY_RESPONSE_BYTES,
 };
-use serde::Deserialize;
-
-use crate::RAZORPAY_BASE_URL;
-
-async fn create_contact(
-    name: String,
-    email: String,
-    contact: String,
-    auth: String,
-) -> Result<String, String> {
-    let headers = vec![
-        HttpHeader {
-            name: "Content-Type".to_string(),
-            value: "application/json".to_string(),
-        },
-        HttpHeader {
-            name: "Authorization".to_string(),
-            value: format!("Basic {}", auth),
-        },
-    ];
-
-    let body = serde_json::json!({
-        "name": name,
-        "email": email,
-        "contact": contact,
-    });
-
-    let body_bytes = serde_json::to_vec(&body).unwrap();
-
-    let request = CanisterHttpRequestArgument {
-        url: format!("{}contacts", RAZORPAY_BASE_URL),
-        max_response_bytes: None,
-        method: HttpMethod::POST,
-        headers,
-        body: Some(body_bytes),
-        transform: None,
-    };
-
-    let response = http_request(request, 1_603_150_400).await;
-
-    match response {
-        Ok(response) => {
-            let response_body = String::from_utf8(response.0.body).unwrap();
-            let response_json: serde_json::Value = serde_json::from_str(&response_body).unwrap();
-            ic_cdk::println!("response_json: {:?}", response_json);
-            let id = response_json["id"].as_str().unwrap();
-
-            Ok(id.to_string())
+
+/// Where a payout is sent.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum PayoutDestination {
+    BankAccount {
+        name: String,
+        ifsc: String,
+        account_number: String,
+    },
+    /// A UPI virtual payment address, such as `user@upi`.
+    Vpa {
+        address: String,
+    },
+    Card {
+        name: String,
+        number: String,
+    },
+}
+
+impl PayoutDestination {
+    /// Identifies the destination, to match it with the fund account created for it. Card
+    /// numbers are keyed by their HMAC-SHA256 under `secret`, which must stay private: only a
+    /// few of their digits are unknown, so a plain hash could be reversed by trying them all.
+    pub fn key(&self, secret: &[u8]) -> String {
+        match self {
+            Self::BankAccount {
+                ifsc,
+                account_number,
+                ..
+            } => format!("bank_account:{ifsc}:{account_number}"),
+            Self::Vpa { address } => format!("vpa:{}", address.to_lowercase()),
+            Self::Card { number, .. } => {
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+                mac.update(number.as_bytes());
+                format!("card:{}", hex::encode(mac.finalize().into_bytes()))
+            }
         }
-        Err(err) => Err(format!("Error creating contact: {}", err.1)),
     }
-}
 
-async fn create_fund_account(
-    name: String,
-    ifsc: String,
-    account_number: String,
-    contact_id: String,
-    auth: String,
-) -> Result<String, String> {
-    let headers = vec![
-        HttpHeader {
-            name: "Content-Type".to_string(),
-            value: "application/json".to_string(),
-        },
-        HttpHeader {
-            name: "Authorization".to_string(),
-            value: format!("Basic {}", auth),
-        },
-    ];
-
-    let body = serde_json::json!({
-        "contact_id": contact_id,
-        "account_type": "bank_account",
-        "bank_account" : {
-          "ifsc": ifsc,
-          "name": name,
-          "account_number": account_number,
-        },
-    });
-
-    let body_bytes = serde_json::to_vec(&body).unwrap();
-
-    let request = CanisterHttpRequestArgument {
-        url: format!("{}fund_accounts", RAZORPAY_BASE_URL),
-        max_response_bytes: None,
-        method: HttpMethod::POST,
-        headers,
-        body: Some(body_bytes),
-        transform: None,
-    };
-
-    let response = http_request(request, 1_603_184_000).await;
-
-    match response {
-        Ok(response) => {
-            let response_body = String::from_utf8(response.0.body).unwrap();
-            let response_json: serde_json::Value = serde_json::from_str(&response_body).unwrap();
-            ic_cdk::println!("response_json: {:?}", response_json);
-            let id = response_json["id"].as_str().unwrap();
-
-            Ok(id.to_string())
+    /// The details of the fund account paying out to the destination.
+    pub fn fund_account_details(&self) -> FundAccountDetails {
+        match self.clone() {
+            Self::BankAccount {
+                name,
+                ifsc,
+                account_number,
+            } => FundAccountDetails::BankAccount {
+                bank_account: BankAccount {
+                    name,
+                    ifsc,
+                    account_number,
+                },
+            },
+            Self::Vpa { address } => FundAccountDetails::Vpa {
+                vpa: Vpa { address },
+            },
+            Self::Card { name, number } => FundAccountDetails::Card {
+                card: Card { name, number },
+            },
         }
-        Err(err) => Err(format!("Error creating fund account: {}", err.1)),
     }
+
+    /// The modes that can pay out to the destination.
+    pub fn modes(&self) -> &'static [PayoutMode] {
+        match self {
+            Self::BankAccount { .. } => &[PayoutMode::Imps, PayoutMode::Neft, PayoutMode::Rtgs],
+            Self::Vpa { .. } => &[PayoutMode::Upi],
+            Self::Card { .. } => &[PayoutMode::Imps, PayoutMode::Neft],
+        }
+    }
+}
+
+/// How a payout is transferred.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayoutMode {
+    Imps,
+    Neft,
+    Rtgs,
+    Upi,
 }
 
-async fn create_payout(
-    account_number: String,
-    fund_account_id: String,
-    amount: u64,
-    auth: String,
-) -> Result<String, String> {
-    let headers = vec![
-        HttpHeader {
-            name: "Content-Type".to_string(),
-            value: "application/json".to_string(),
-        },
-        HttpHeader {
-            name: "Authorization".to_string(),
-            value: format!("Basic {}", auth),
-        },
-    ];
-
-    let body = serde_json::json!({
-        "fund_account_id": fund_account_id,
-        "account_number": account_number,
-        "amount": amount,
-        "currency": "INR",
-        "mode": "IMPS",
-        "purpose": "payout"
-    });
-
-    let body_bytes = serde_json::to_vec(&body).unwrap();
-
-    let request = CanisterHttpRequestArgument {
-        url: format!("{}payouts", RAZORPAY_BASE_URL),
-        max_response_bytes: None,
-        method: HttpMethod::POST,
-        headers,
-        body: Some(body_bytes),
-        transform: None,
-    };
-
-    let response = http_request(request, 1_603_172_400).await;
-
-    match response {
-        Ok(response) => {
-            let response_body = String::from_utf8(response.0.body).unwrap();
-            let response_json: serde_json::Value = serde_json::from_str(&response_body).unwrap();
-            ic_cdk::println!("response_json: {:?}", response_json);
-            let id = response_json["id"].as_str().unwrap();
-
-            Ok(id.to_string())
+impl PayoutMode {
+    /// The mode as named by the Razorpay API.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Imps => "IMPS",
+            Self::Neft => "NEFT",
+            Self::Rtgs => "RTGS",
+            Self::Upi => "UPI",
+        }
+    }
+
+    /// The smallest and largest amounts the mode can transfer, in paise: INR 5 lakh by IMPS,
+    /// INR 1 lakh by UPI and at least INR 2 lakh by RTGS.
+    pub fn limits(self) -> (u64, Option<u64>) {
+        match self {
+            Self::Imps => (100, Some(50_000_000)),
+            Self::Neft => (100, None),
+            Self::Rtgs => (20_000_000, None),
+            Self::Upi => (100, Some(10_000_000)),
         }
-        Err(err) => Err(format!("Error creating payout: {}", err.1)),
     }
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct PayoutArgs {
-    name: String,
-    email: String,
-    contact: String,
-    ifsc: String,
-    account_number: String,
-    amount: u64,
+    pub name: String,
+    pub email: String,
+    pub contact: String,
+    pub destination: PayoutDestination,
+    pub mode: PayoutMode,
+}
+
+impl PayoutArgs {
+    /// Checks that the mode can pay out `amount` paise to the destination.
+    pub fn validate(&self, amount: u64) -> Result<(), String> {
+        let mode = self.mode.as_str();
+        if !self.destination.modes().contains(&self.mode) {
+            return Err(format!("{mode} can't pay out to this destination"));
+        }
+        match self.mode.limits() {
+            (min, _) if amount < min => Err(format!(
+                "{mode} payouts must be at least {min} paise, not {amount}"
+            )),
+            (_, Some(max)) if amount > max => Err(format!(
+                "{mode} payouts must be at most {max} paise, not {amount}"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CreatePayoutRequest {
+    /// The RazorpayX account paying out.
+    pub account_number: String,
+    pub fund_account_id: String,
+    /// Amount in paise.
+    pub amount: u64,
+    pub currency: String,
+    pub mode: String,
+    pub purpose: String,
+    pub reference_id: String,
 }
 
-pub async fn payout(
-    args: PayoutArgs,
-    razorpay_x_acccount: String,
-    auth: String,
-) -> Result<String, String> {
-    let contact_id =
-        create_contact(args.name.clone(), args.email, args.contact, auth.clone()).await;
+/// A payout made from the RazorpayX account.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Payout {
+    pub id: String,
+    pub fund_account_id: Option<String>,
+    /// Amount in paise.
+    pub amount: u64,
+    pub currency: Option<String>,
+    /// Fees charged for the payout, tax included, in paise.
+    pub fees: Option<u64>,
+    /// Tax part of the fees, in paise.
+    pub tax: Option<u64>,
+    /// One of `queued`, `pending`, `processing`, `processed`, `reversed`, `cancelled`,
+    /// `rejected` or `failed`.
+    pub status: String,
+    /// Reference number of the transfer with the bank, once processed.
+    pub utr: Option<String>,
+    pub mode: Option<String>,
+    pub purpose: Option<String>,
+    pub reference_id: Option<String>,
+    /// Reason of a failed payout, if any.
+    pub failure_reason: Option<String>,
+    /// Unix timestamp, in seconds.
+    pub created_at: Option<u64>,
+}
 
-    if contact_id.is_err() {
-        return Err(contact_id.err().unwrap());
+impl Payout {
+    /// Whether the money reached the beneficiary.
+    pub fn is_processed(&self) -> bool {
+        self.status == "processed"
     }
 
-    let contact_id = contact_id.unwrap();
+    /// Whether the payout won't go through anymore, and the money stayed in or went back to
+    /// the RazorpayX account.
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            "reversed" | "cancelled" | "rejected" | "failed"
+        )
+    }
+}
 
-    let create_fund_account = create_fund_account(
-        args.name,
-        args.ifsc,
-        args.account_number.clone(),
-        contact_id,
-        auth.clone(),
-    )
-    .await;
+/// Filters of [`list_payouts`]. Unset fields don't filter.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PayoutFilter {
+    pub status: Option<String>,
+    pub fund_account_id: Option<String>,
+    pub reference_id: Option<String>,
+    /// Unix timestamp, in seconds, of the oldest payout.
+    pub from: Option<u64>,
+    /// Unix timestamp, in seconds, of the newest payout.
+    pub to: Option<u64>,
+    /// Number of payouts to return, at most 100.
+    pub count: Option<u32>,
+    /// Number of payouts to skip.
+    pub skip: Option<u32>,
+}
 
-    if create_fund_account.is_err() {
-        return Err(create_fund_account.err().unwrap());
+impl PayoutFilter {
+    fn query(&self, account_number: &str) -> String {
+        query_string([
+            ("account_number", Some(account_number.to_string())),
+            ("status", self.status.clone()),
+            ("fund_account_id", self.fund_account_id.clone()),
+            ("reference_id", self.reference_id.clone()),
+            ("from", self.from.map(|from| from.to_string())),
+            ("to", self.to.map(|to| to.to_string())),
+            ("count", self.count.map(|count| count.to_string())),
+            ("skip", self.skip.map(|skip| skip.to_string())),
+        ])
     }
+}
 
-    let fund_account_id = create_fund_account.unwrap();
+/// Header of the key Razorpay deduplicates payout requests by.
+pub const PAYOUT_IDEMPOTENCY_HEADER: &str = "X-Payout-Idempotency";
+
+impl RazorpayClient {
+    /// Creates a payout, once per `idempotency_key`: requests with a key already used return
+    /// the payout it created.
+    pub async fn create_payout(
+        &self,
+        request: &CreatePayoutRequest,
+        idempotency_key: &str,
+    ) -> Result<Payout, RazorpayError> {
+        self.post_idempotent(
+            "payouts",
+            request,
+            PAYOUT_IDEMPOTENCY_HEADER,
+            idempotency_key,
+            MAX_ENTITY_RESPONSE_BYTES,
+        )
+        .await
+    }
 
-    let create_payout =
-        create_payout(razorpay_x_acccount, fund_account_id, args.amount, auth).await;
+    /// Pays out `amount` paise to a fund account, from the RazorpayX account
+    /// `razorpay_x_acccount`, once per `idempotency_key`. The payout can be found again by
+    /// `reference_id`, see [`PayoutFilter`].
+    pub async fn payout(
+        &self,
+        fund_account_id: String,
+        amount: u64,
+        mode: PayoutMode,
+        reference_id: String,
+        razorpay_x_acccount: String,
+        idempotency_key: &str,
+    ) -> Result<Payout, RazorpayError> {
+        let request = CreatePayoutRequest {
+            account_number: razorpay_x_acccount,
+            fund_account_id,
+            amount,
+            currency: "INR".to_string(),
+            mode: mode.as_str().to_string(),
+            purpose: "payout".to_string(),
+            reference_id,
+        };
+        self.create_payout(&request, idempotency_key).await
+    }
 
-    if create_payout.is_err() {
-        return Err(create_payout.err().unwrap());
+    pub async fn fetch_payout(&self, payout_id: &str) -> Result<Payout, RazorpayError> {
+        self.get(&format!("payouts/{payout_id}"), MAX_ENTITY_RESPONSE_BYTES)
+            .await
     }
 
-    let payout_id = create_payout.unwrap();
+    /// Returns the payouts made from a RazorpayX account, newest first.
+    pub async fn list_payouts(
+        &self,
+        razorpay_x_acccount: &str,
+        filter: &PayoutFilter,
+    ) -> Result<Vec<Payout>, RazorpayError> {
+        let payouts: Collection<Payout> = self
+            .get(
+                &format!("payouts?{}", filter.query(razorpay_x_acccount)),
+                MAX_COLLECTION_RESPONSE_BYTES,
+            )
+            .await?;
+        Ok(payouts.items)
+    }
 
-    Ok(payout_id)
+    /// Cancels a payout. Only queued payouts can be cancelled.
+    pub async fn cancel_payout(&self, payout_id: &str) -> Result<Payout, RazorpayError> {
+        self.post(
+            &format!("payouts/{payout_id}/cancel"),
+            &serde_json::json!({}),
+            MAX_ENTITY_RESPONSE_BYTES,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_payout() {
+        let body = r#"{
+            "id": "pout_00000000000001",
+            "entity": "payout",
+            "fund_account_id": "fa_00000000000001",
+            "amount": 100000,
+            "currency": "INR",
+            "fees": 590,
+            "tax": 90,
+            "status": "reversed",
+            "utr": null,
+            "mode": "IMPS",
+            "purpose": "payout",
+            "reference_id": "offramp-1",
+            "failure_reason": "Beneficiary bank is down",
+            "created_at": 1700000000
+        }"#;
+
+        let payout: Payout = serde_json::from_str(body).unwrap();
+        assert_eq!(payout.fees, Some(590));
+        assert_eq!(payout.utr, None);
+        assert_eq!(payout.reference_id.as_deref(), Some("offramp-1"));
+        assert!(payout.is_failed());
+        assert!(!payout.is_processed());
+    }
+
+    #[test]
+    fn test_validate_payout_args() {
+        let args = |destination, mode| PayoutArgs {
+            name: "Test User".to_string(),
+            email: "user@example.com".to_string(),
+            contact: "9000000000".to_string(),
+            destination,
+            mode,
+        };
+        let bank_account = PayoutDestination::BankAccount {
+            name: "Test User".to_string(),
+            ifsc: "HDFC0000053".to_string(),
+            account_number: "765432123456789".to_string(),
+        };
+        let vpa = PayoutDestination::Vpa {
+            address: "user@upi".to_string(),
+        };
+
+        assert!(args(vpa.clone(), PayoutMode::Upi).validate(100_000).is_ok());
+        assert!(args(vpa.clone(), PayoutMode::Imps)
+            .validate(100_000)
+            .is_err());
+        assert!(args(vpa, PayoutMode::Upi).validate(20_000_000).is_err());
+        assert!(args(bank_account.clone(), PayoutMode::Upi)
+            .validate(100_000)
+            .is_err());
+        assert!(args(bank_account.clone(), PayoutMode::Imps)
+            .validate(50_000_000)
+            .is_ok());
+        assert!(args(bank_account.clone(), PayoutMode::Imps)
+            .validate(50_000_001)
+            .is_err());
+        assert!(args(bank_account.clone(), PayoutMode::Rtgs)
+            .validate(100_000)
+            .is_err());
+        assert!(args(bank_account.clone(), PayoutMode::Neft)
+            .validate(100_000_000)
+            .is_ok());
+        assert!(args(bank_account, PayoutMode::Neft).validate(0).is_err());
+    }
+
+    #[test]
+    fn test_destination_key_hides_card_number() {
+        let card = PayoutDestination::Card {
+            name: "Test User".to_string(),
+            number: "4111111111111111".to_string(),
+        };
+        let key = card.key(b"secret");
+        assert!(key.starts_with("card:"));
+        assert!(!key.contains("4111111111111111"));
+        assert_eq!(key, card.key(b"secret"));
+        assert_ne!(key, card.key(b"other secret"));
+    }
+
+    #[test]
+    fn test_payout_filter_query() {
+        assert_eq!(
+            PayoutFilter::default().query("7878780080316316"),
+            "account_number=7878780080316316"
+        );
+
+        let filter = PayoutFilter {
+            status: Some("processing".to_string()),
+            reference_id: Some("offramp-1".to_string()),
+            count: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.query("7878780080316316"),
+            "account_number=7878780080316316&status=processing&reference_id=offramp-1&count=10"
+        );
+    }
 }
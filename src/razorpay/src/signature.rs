@@ -0,0 +1,82 @@
+//! Signatures Razorpay attaches to checkout callbacks and webhooks.
+//!
+//! Both are hex encoded HMAC-SHA256 digests: of `{order_id}|{payment_id}` keyed by the API key
+//! secret for checkout, and of the raw body keyed by the webhook secret for webhooks.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Checks the signature Razorpay Checkout returns for a payment, in constant time.
+pub fn verify_payment_signature(
+    order_id: &str,
+    payment_id: &str,
+    signature: &str,
+    key_secret: &str,
+) -> bool {
+    verify_hmac_sha256(
+        format!("{order_id}|{payment_id}").as_bytes(),
+        signature,
+        key_secret,
+    )
+}
+
+/// Checks the signature of a webhook body, in constant time.
+pub fn verify_webhook_signature(body: &[u8], signature: &str, secret: &str) -> bool {
+    verify_hmac_sha256(body, signature, secret)
+}
+
+fn verify_hmac_sha256(message: &[u8], signature: &str, secret: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(message);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_payment_signature() {
+        let signature = "6fefab2b7760510190b86ee4b26e3318c2d0d4a803f6a25aeb2a35147506bcba";
+
+        assert!(verify_payment_signature(
+            "order_00000000000001",
+            "pay_00000000000001",
+            signature,
+            "secret"
+        ));
+        assert!(!verify_payment_signature(
+            "order_00000000000002",
+            "pay_00000000000001",
+            signature,
+            "secret"
+        ));
+        assert!(!verify_payment_signature(
+            "order_00000000000001",
+            "pay_00000000000002",
+            signature,
+            "secret"
+        ));
+        assert!(!verify_payment_signature(
+            "order_00000000000001",
+            "pay_00000000000001",
+            signature,
+            "other secret"
+        ));
+    }
+
+    #[test]
+    fn test_verify_webhook_signature() {
+        let body = br#"{"event":"payment.captured"}"#;
+        let signature = "8308ed257604fd2e3a5f31e3ca7b5a85c225df5b0dc02b63004414e0a99c1e6e";
+
+        assert!(verify_webhook_signature(body, signature, "secret"));
+        assert!(!verify_webhook_signature(body, signature, "other secret"));
+        assert!(!verify_webhook_signature(b"{}", signature, "secret"));
+        assert!(!verify_webhook_signature(body, "not hex", "secret"));
+    }
+}
@@ -0,0 +1,85 @@
+//! Razorpay webhooks.
+//!
+//! Razorpay signs the raw body of each webhook with the webhook secret and sends the signature
+//! in the `X-Razorpay-Signature` header, see [`crate::verify_webhook_signature`]. Webhooks are
+//! delivered at least once, with a unique event id in the `X-Razorpay-Event-Id` header.
+
+use serde::Deserialize;
+
+use crate::{Payment, Payout};
+
+pub const SIGNATURE_HEADER: &str = "x-razorpay-signature";
+pub const EVENT_ID_HEADER: &str = "x-razorpay-event-id";
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Entity<T> {
+    pub entity: T,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct WebhookPayload {
+    pub payment: Option<Entity<Payment>>,
+    pub payout: Option<Entity<Payout>>,
+}
+
+/// The body of a webhook.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WebhookEvent {
+    /// The event name, such as `payment.captured`.
+    pub event: String,
+    #[serde(default)]
+    pub payload: WebhookPayload,
+    /// Unix timestamp of the event, in seconds.
+    pub created_at: u64,
+}
+
+impl WebhookEvent {
+    pub fn payment(&self) -> Option<&Payment> {
+        self.payload.payment.as_ref().map(|payment| &payment.entity)
+    }
+
+    pub fn payout(&self) -> Option<&Payout> {
+        self.payload.payout.as_ref().map(|payout| &payout.entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_webhook_event() {
+        let body = r#"{
+            "entity": "event",
+            "event": "payout.processed",
+            "contains": ["payout"],
+            "payload": {
+                "payout": {
+                    "entity": {
+                        "id": "pout_00000000000001",
+                        "entity": "payout",
+                        "fund_account_id": "fa_00000000000001",
+                        "amount": 100000,
+                        "currency": "INR",
+                        "fees": 590,
+                        "tax": 90,
+                        "status": "processed",
+                        "utr": "000000000001",
+                        "mode": "IMPS",
+                        "purpose": "payout",
+                        "reference_id": "offramp-1",
+                        "failure_reason": null,
+                        "created_at": 1700000000
+                    }
+                }
+            },
+            "created_at": 1700000000
+        }"#;
+
+        let event: WebhookEvent = serde_json::from_str(body).unwrap();
+        assert_eq!(event.event, "payout.processed");
+        assert_eq!(event.payment(), None);
+        assert_eq!(event.payout().unwrap().id, "pout_00000000000001");
+        assert_eq!(event.payout().unwrap().amount, 100_000);
+    }
+}